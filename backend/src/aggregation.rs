@@ -0,0 +1,181 @@
+use super::interfaces::HistogramInterval;
+use chrono::{Datelike, TimeZone, Utc};
+use std::collections::{BTreeMap, HashMap};
+use tantivy::{
+    collector::{Collector, SegmentCollector},
+    fastfield::{DynamicFastFieldReader, FastFieldReader},
+    schema::Field,
+    DateTime, DocId, Score, SegmentOrdinal, SegmentReader,
+};
+
+/// Seconds of a day
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+/// 1970-01-01 is a Thursday, 3 days after the Monday starting its week
+const EPOCH_WEEKDAY_OFFSET: i64 = 3 * SECONDS_PER_DAY;
+
+/// Start of the bucket of `interval` containing `timestamp` (in seconds),
+/// in seconds
+pub fn bucket_start(interval: HistogramInterval, timestamp: i64) -> i64 {
+    let floor = |width: i64, offset: i64| (timestamp + offset).div_euclid(width) * width - offset;
+    match interval {
+        HistogramInterval::Day => floor(SECONDS_PER_DAY, 0),
+        HistogramInterval::Week => floor(7 * SECONDS_PER_DAY, EPOCH_WEEKDAY_OFFSET),
+        HistogramInterval::Month | HistogramInterval::Year => {
+            let time = Utc.timestamp(timestamp, 0);
+            let month = if interval == HistogramInterval::Month {
+                time.month()
+            } else {
+                1
+            };
+            Utc.ymd(time.year(), month, 1).and_hms(0, 0, 0).timestamp()
+        }
+    }
+}
+
+/// Collector counting hits per bucket of the date fast field
+///
+/// Its fruit maps start of each non-empty bucket (in seconds) to count.
+pub struct DateHistogramCollector {
+    field: Field,
+    interval: HistogramInterval,
+}
+
+impl DateHistogramCollector {
+    pub fn new(field: Field, interval: HistogramInterval) -> Self {
+        Self { field, interval }
+    }
+}
+
+pub struct DateHistogramSegmentCollector {
+    reader: DynamicFastFieldReader<DateTime>,
+    interval: HistogramInterval,
+    /// Counts by timestamp, which are bucketed on harvest, since a segment
+    /// usually has far fewer distinct timestamps than hits
+    counts: HashMap<i64, u64>,
+}
+
+impl Collector for DateHistogramCollector {
+    type Fruit = BTreeMap<i64, u64>;
+    type Child = DateHistogramSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(DateHistogramSegmentCollector {
+            reader: segment_reader.fast_fields().date(self.field)?,
+            interval: self.interval,
+            counts: HashMap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        let mut buckets = BTreeMap::new();
+        for segment_buckets in segment_fruits {
+            for (start, count) in segment_buckets {
+                *buckets.entry(start).or_insert(0) += count;
+            }
+        }
+        Ok(buckets)
+    }
+}
+
+impl SegmentCollector for DateHistogramSegmentCollector {
+    type Fruit = BTreeMap<i64, u64>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        *self
+            .counts
+            .entry(self.reader.get(doc).timestamp())
+            .or_insert(0) += 1;
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let mut buckets = BTreeMap::new();
+        for (timestamp, count) in self.counts {
+            *buckets
+                .entry(bucket_start(self.interval, timestamp))
+                .or_insert(0) += count;
+        }
+        buckets
+    }
+}
+
+/// Collector counting hits per range of the u64 fast field
+///
+/// Ranges are split by ascending `boundaries`, so that its fruit has one
+/// more count than boundaries, the first of which counts values below the
+/// first boundary.
+pub struct RangeHistogramCollector {
+    field: Field,
+    boundaries: Vec<u64>,
+}
+
+impl RangeHistogramCollector {
+    pub fn new(field: Field, boundaries: Vec<u64>) -> Self {
+        Self { field, boundaries }
+    }
+}
+
+pub struct RangeHistogramSegmentCollector {
+    reader: DynamicFastFieldReader<u64>,
+    boundaries: Vec<u64>,
+    counts: Vec<u64>,
+}
+
+impl Collector for RangeHistogramCollector {
+    type Fruit = Vec<u64>;
+    type Child = RangeHistogramSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(RangeHistogramSegmentCollector {
+            reader: segment_reader.fast_fields().u64(self.field)?,
+            boundaries: self.boundaries.clone(),
+            counts: vec![0; self.boundaries.len() + 1],
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Self::Fruit>) -> tantivy::Result<Self::Fruit> {
+        let mut counts = vec![0; self.boundaries.len() + 1];
+        for segment_counts in segment_fruits {
+            for (count, segment_count) in counts.iter_mut().zip(segment_counts) {
+                *count += segment_count;
+            }
+        }
+        Ok(counts)
+    }
+}
+
+impl SegmentCollector for RangeHistogramSegmentCollector {
+    type Fruit = Vec<u64>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        let value = self.reader.get(doc);
+        let bucket = self
+            .boundaries
+            .partition_point(|boundary| *boundary <= value);
+        self.counts[bucket] += 1;
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+#[path = "./aggregation_test.rs"]
+mod aggregation_test;
@@ -0,0 +1,30 @@
+use super::*;
+
+#[test]
+fn test_bucket_start() {
+    // 2021-03-17 (Wednesday) 12:34:56 UTC
+    let timestamp = Utc.ymd(2021, 3, 17).and_hms(12, 34, 56).timestamp();
+    let expected = [
+        (HistogramInterval::Day, Utc.ymd(2021, 3, 17)),
+        (HistogramInterval::Week, Utc.ymd(2021, 3, 15)),
+        (HistogramInterval::Month, Utc.ymd(2021, 3, 1)),
+        (HistogramInterval::Year, Utc.ymd(2021, 1, 1)),
+    ];
+    for (interval, start) in expected {
+        assert_eq!(
+            bucket_start(interval, timestamp),
+            start.and_hms(0, 0, 0).timestamp()
+        );
+    }
+
+    // Before epoch
+    let timestamp = Utc.ymd(1969, 12, 31).and_hms(23, 0, 0).timestamp();
+    assert_eq!(
+        bucket_start(HistogramInterval::Day, timestamp),
+        -SECONDS_PER_DAY
+    );
+    assert_eq!(
+        bucket_start(HistogramInterval::Week, timestamp),
+        Utc.ymd(1969, 12, 29).and_hms(0, 0, 0).timestamp()
+    );
+}
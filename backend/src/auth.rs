@@ -0,0 +1,78 @@
+use super::config::AuthConfig;
+use super::interfaces::UserError;
+use actix_web::dev::ServiceRequest;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Header carrying API key of client
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Role of an API key. A role is granted everything of roles before it
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum Role {
+    /// Search endpoints
+    Read,
+    /// Endpoints under `/api/admin`
+    Admin,
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "read" => Ok(Role::Read),
+            "admin" => Ok(Role::Admin),
+            _ => Err(format!("Unknown role: {}.", role)),
+        }
+    }
+}
+
+/// Authenticates clients by API key sent in [`API_KEY_HEADER`]
+pub struct Authenticator {
+    roles: HashMap<String, Role>,
+    require_read_key: bool,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig) -> Self {
+        Self {
+            roles: config
+                .api_keys
+                .iter()
+                .map(|api_key| (api_key.key.clone(), api_key.role))
+                .collect(),
+            require_read_key: config.require_read_key,
+        }
+    }
+
+    /// Check that client sending `request` is granted `required` role
+    ///
+    /// Rejects with 401 if API key is absent or unknown, and with 403 if
+    /// role of the key is insufficient. [`Role::Read`] requires no key
+    /// unless `require_read_key` is configured.
+    pub fn check_request(&self, request: &ServiceRequest, required: Role) -> Result<(), UserError> {
+        if required == Role::Read && !self.require_read_key {
+            return Ok(());
+        }
+        let role = request
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|api_key| api_key.to_str().ok())
+            .and_then(|api_key| self.roles.get(api_key))
+            .ok_or_else(|| UserError::Unauthorized("Missing or unknown API key".to_string()))?;
+        if *role < required {
+            return Err(UserError::Forbidden(format!(
+                "API key of role {:?} is not granted {:?}",
+                role, required
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[path = "./auth_test.rs"]
+mod auth_test;
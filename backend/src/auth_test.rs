@@ -0,0 +1,77 @@
+use super::*;
+use crate::config::ApiKeyConfig;
+use actix_web::{http::StatusCode, test, web, App, HttpResponse};
+
+fn create_authenticator(require_read_key: bool) -> Authenticator {
+    Authenticator::new(&AuthConfig {
+        api_keys: vec![
+            "reader:read".parse().unwrap(),
+            "root:admin".parse().unwrap(),
+        ],
+        require_read_key,
+    })
+}
+
+#[test]
+fn test_parse_api_key() {
+    let api_key: ApiKeyConfig = "a:b:admin".parse().unwrap();
+    assert_eq!(api_key.key, "a:b");
+    assert_eq!(api_key.role, Role::Admin);
+    assert!("key".parse::<ApiKeyConfig>().is_err());
+    assert!("key:root".parse::<ApiKeyConfig>().is_err());
+}
+
+#[actix_rt::test]
+async fn test_admin_scope() {
+    let authenticator = web::Data::new(create_authenticator(false));
+    let mut app = test::init_service(
+        App::new().service(
+            web::scope("/api/admin")
+                .wrap_fn(crate::guard(move |request: &ServiceRequest| {
+                    authenticator.check_request(request, Role::Admin)
+                }))
+                .route("/reload", web::post().to(HttpResponse::NoContent)),
+        ),
+    )
+    .await;
+    for (api_key, status) in [
+        (None, StatusCode::UNAUTHORIZED),
+        (Some("unknown"), StatusCode::UNAUTHORIZED),
+        (Some("reader"), StatusCode::FORBIDDEN),
+        (Some("root"), StatusCode::NO_CONTENT),
+    ] {
+        let mut request = test::TestRequest::post().uri("/api/admin/reload");
+        if let Some(api_key) = api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        let response = test::call_service(&mut app, request.to_request()).await;
+        assert_eq!(response.status(), status, "API key {:?}", api_key);
+    }
+}
+
+#[test]
+fn test_read_key() {
+    let request = |api_key: Option<&str>| {
+        let mut request = test::TestRequest::default();
+        if let Some(api_key) = api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        request.to_srv_request()
+    };
+    let authenticator = create_authenticator(false);
+    assert!(authenticator
+        .check_request(&request(None), Role::Read)
+        .is_ok());
+
+    let authenticator = create_authenticator(true);
+    assert!(matches!(
+        authenticator.check_request(&request(None), Role::Read),
+        Err(UserError::Unauthorized(_))
+    ));
+    assert!(authenticator
+        .check_request(&request(Some("reader")), Role::Read)
+        .is_ok());
+    assert!(authenticator
+        .check_request(&request(Some("root")), Role::Read)
+        .is_ok());
+}
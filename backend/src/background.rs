@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Interval of checking whether threads finished while shutting down
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A thread of [`BackgroundThreads`] with its cancellation flag
+struct BackgroundThread {
+    cancelled: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+/// Threads outliving the requests spawning them, e.g., exports and the
+/// popularity write back, which are cancelled and waited for on shutdown
+///
+/// Each thread gets a flag set once it should stop, and it is unparked at
+/// the same time, so that threads sleeping with `thread::park_timeout` wake
+/// up right away.
+#[derive(Default)]
+pub struct BackgroundThreads {
+    threads: Mutex<Vec<BackgroundThread>>,
+}
+
+impl BackgroundThreads {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` on a thread of its own, passing the flag telling it to stop
+    pub fn spawn(&self, f: impl FnOnce(Arc<AtomicBool>) + Send + 'static) {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let cancelled = cancelled.clone();
+            thread::spawn(move || f(cancelled))
+        };
+        let mut threads = self.threads.lock().unwrap();
+        threads.retain(|thread| !thread.handle.is_finished());
+        threads.push(BackgroundThread { cancelled, handle });
+    }
+
+    /// Cancel all threads and wait for them to finish within `timeout`
+    ///
+    /// Returns count of threads still running after `timeout`, which are
+    /// left detached.
+    pub fn shutdown(&self, timeout: Duration) -> usize {
+        let threads = std::mem::take(&mut *self.threads.lock().unwrap());
+        for thread in &threads {
+            thread.cancelled.store(true, Ordering::Relaxed);
+            thread.handle.thread().unpark();
+        }
+        let expires_at = Instant::now() + timeout;
+        let mut running = threads;
+        loop {
+            let (finished, unfinished) = running
+                .into_iter()
+                .partition::<Vec<_>, _>(|thread| thread.handle.is_finished());
+            for thread in finished {
+                let _ = thread.handle.join();
+            }
+            running = unfinished;
+            if running.is_empty() || Instant::now() >= expires_at {
+                return running.len();
+            }
+            thread::sleep(SHUTDOWN_POLL_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./background_test.rs"]
+mod background_test;
@@ -0,0 +1,25 @@
+use super::*;
+
+#[test]
+fn test_shutdown_cancels_threads() {
+    let background_threads = BackgroundThreads::new();
+    let finished = Arc::new(AtomicBool::new(false));
+    {
+        let finished = finished.clone();
+        background_threads.spawn(move |cancelled| {
+            while !cancelled.load(Ordering::Relaxed) {
+                thread::park_timeout(Duration::from_secs(60));
+            }
+            finished.store(true, Ordering::Relaxed);
+        });
+    }
+    assert_eq!(background_threads.shutdown(Duration::from_secs(10)), 0);
+    assert!(finished.load(Ordering::Relaxed));
+}
+
+#[test]
+fn test_shutdown_timeout() {
+    let background_threads = BackgroundThreads::new();
+    background_threads.spawn(|_| thread::sleep(Duration::from_millis(500)));
+    assert_eq!(background_threads.shutdown(Duration::from_millis(0)), 1);
+}
@@ -0,0 +1,134 @@
+use super::deadline::Deadline;
+use super::interfaces::CacheStats;
+use lru::LruCache;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Cached value, tagged with the searcher generation computing it
+struct CacheEntry<V> {
+    generation: u64,
+    created: Instant,
+    value: V,
+}
+
+/// In-process LRU cache of query results
+///
+/// An entry is treated as missing if it is older than `ttl`, or if it is
+/// computed by a searcher of another generation (i.e., index is reloaded).
+pub struct QueryCache<K: Hash + Eq, V: Clone> {
+    entries: Mutex<LruCache<K, CacheEntry<V>>>,
+    ttl: RwLock<Duration>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Hash + Eq, V: Clone> QueryCache<K, V> {
+    /// Create cache holding at most `capacity` entries
+    ///
+    /// If `capacity` is 0, nothing is cached.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl: RwLock::new(ttl),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Get cached value computed by searcher of `generation`
+    pub fn get(&self, key: &K, generation: u64) -> Option<V> {
+        let ttl = self.ttl();
+        let value = self.entries.lock().ok().and_then(|mut entries| {
+            let valid = match entries.peek(key) {
+                Some(entry) => entry.generation == generation && entry.created.elapsed() < ttl,
+                None => return None,
+            };
+            if valid {
+                entries.get(key).map(|entry| entry.value.clone())
+            } else {
+                entries.pop(key);
+                None
+            }
+        });
+        if value.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+        value
+    }
+
+    /// Cache value computed by searcher of `generation`
+    ///
+    /// Values computed past deadline may be partial, and are not cached.
+    pub fn insert(&self, key: K, generation: u64, value: V) {
+        if Deadline::current_expired() {
+            return;
+        }
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.cap() == 0 {
+                return;
+            }
+            entries.put(
+                key,
+                CacheEntry {
+                    generation,
+                    created: Instant::now(),
+                    value,
+                },
+            );
+        }
+    }
+
+    /// Get cached value, or compute and cache it
+    pub fn get_or_try_insert_with<E>(
+        &self,
+        key: K,
+        generation: u64,
+        compute: impl FnOnce() -> Result<V, E>,
+    ) -> Result<V, E> {
+        if let Some(value) = self.get(&key, generation) {
+            return Ok(value);
+        }
+        let value = compute()?;
+        self.insert(key, generation, value.clone());
+        Ok(value)
+    }
+
+    /// Change capacity and TTL, evicting least recently used entries if
+    /// shrunk
+    pub fn resize(&self, capacity: usize, ttl: Duration) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.resize(capacity);
+        }
+        if let Ok(mut current_ttl) = self.ttl.write() {
+            *current_ttl = ttl;
+        }
+    }
+
+    fn ttl(&self) -> Duration {
+        self.ttl.read().map_or(Duration::ZERO, |ttl| *ttl)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let (len, capacity) = match self.entries.lock() {
+            Ok(entries) => (entries.len(), entries.cap()),
+            Err(_) => (0, 0),
+        };
+        CacheStats {
+            hits,
+            misses,
+            hit_ratio: if hits + misses == 0 {
+                0.0
+            } else {
+                hits as f64 / (hits + misses) as f64
+            },
+            len,
+            capacity,
+        }
+    }
+}
@@ -1,30 +1,692 @@
+use std::fmt;
 use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
 
-use serde::Deserialize;
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use toml::Value;
 
-#[derive(Deserialize)]
+use crate::auth::Role;
+
+/// Default path of config file, which is optional unless given by `--config`
+const DEFAULT_CONFIG_PATH: &str = "./backend-config.toml";
+
+/// Prefix of env vars overriding config, e.g., `UDIAB_PORT=8080`
+const ENV_PREFIX: &str = "UDIAB_";
+
+/// Separator of section and field in env var names, e.g.,
+/// `UDIAB_SEARCH_POOL__THREADS=8` overrides `threads` of `[search_pool]`
+const ENV_SECTION_SEPARATOR: &str = "__";
+
+/// Env var of API keys appended to [`AuthConfig::api_keys`]
+const API_KEYS_ENV: &str = "UDIAB_API_KEYS";
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Config {
     pub host: String,
     pub port: u16,
     pub index_store_directory: String,
+    /// Path of JSON-lines file recording impressions and clicks
+    pub tracking_log_path: String,
+    /// Seconds to wait for in-flight requests, exports and background
+    /// threads to finish on SIGTERM
+    pub shutdown_timeout: u64,
+    /// Level of logged messages, one of `off`, `error`, `warn`, `info`,
+    /// `debug` and `trace`
+    pub log_level: String,
+    pub popularity: PopularityConfig,
+    pub synonyms: SynonymsConfig,
+    pub cache: CacheConfig,
+    pub limits: LimitsConfig,
+    pub search_pool: SearchPoolConfig,
+    pub rate_limit: RateLimitConfig,
+    pub auth: AuthConfig,
+    pub http: HttpConfig,
+    pub tls: TlsConfig,
 }
 
-impl Config {
-    /// Retrieve config at ./backend-config.toml
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: "0.0.0.0".to_string(),
+            port: 80,
+            index_store_directory: "./index".to_string(),
+            tracking_log_path: "./tracking.jsonl".to_string(),
+            shutdown_timeout: 30,
+            log_level: "info".to_string(),
+            popularity: PopularityConfig::default(),
+            synonyms: SynonymsConfig::default(),
+            cache: CacheConfig::default(),
+            limits: LimitsConfig::default(),
+            search_pool: SearchPoolConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            auth: AuthConfig::default(),
+            http: HttpConfig::default(),
+            tls: TlsConfig::default(),
+        }
+    }
+}
+
+/// Config of internal popularity counter
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PopularityConfig {
+    /// Path of JSON file persisting click counts.
+    ///
+    /// If empty, click counts are kept in memory only and lost when backend
+    /// stops, see [`PopularityConfig::store_file`].
+    pub store_path: Option<String>,
+    /// Interval (in seconds) of writing click counts back for hot scoring
+    pub write_back_interval: u64,
+    /// Weight of one click compared to one scraped like
+    pub click_weight: u64,
+}
+
+impl Default for PopularityConfig {
+    fn default() -> Self {
+        Self {
+            store_path: Some("./popularity.json".to_string()),
+            write_back_interval: 60,
+            click_weight: 10,
+        }
+    }
+}
+
+impl PopularityConfig {
+    /// Path of the store of click counts, `None` if `store_path` is empty
     ///
-    /// # Panics
+    /// TOML has no null, so that an empty string is how the store is
+    /// disabled in config.
+    pub fn store_file(&self) -> Option<PathBuf> {
+        non_empty_path(&self.store_path)
+    }
+}
+
+/// `path` unless it is absent or empty
+fn non_empty_path(path: &Option<String>) -> Option<PathBuf> {
+    path.as_deref()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Config of synonyms expanding keys at query time, see
+/// [`crate::synonyms::Synonyms`]
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SynonymsConfig {
+    /// Path of synonym dictionary, reloaded by `POST /api/admin/synonyms/reload`.
     ///
-    /// Will panic if file not exist or format not matched
-    pub fn retrieve_config() -> Self {
-        let config_file_path = "./backend-config.toml";
-        let config_str = fs::read_to_string(&config_file_path).expect(&format!(
-            "Unable to open config file at {}.",
-            &config_file_path
-        ));
-        let config = match toml::from_str(&config_str) {
-            Ok(config) => config,
-            Err(error) => panic!("Config file parse failed: {}", error),
+    /// If empty or there is no such file, keys are not expanded, see
+    /// [`SynonymsConfig::dictionary_file`].
+    pub path: Option<String>,
+    /// Boost of synonym variants of key, relative to key itself
+    pub boost: f32,
+}
+
+impl Default for SynonymsConfig {
+    fn default() -> Self {
+        Self {
+            path: Some("./synonyms.txt".to_string()),
+            boost: 0.5,
+        }
+    }
+}
+
+impl SynonymsConfig {
+    /// Path of synonym dictionary, `None` if `path` is empty, like
+    /// [`PopularityConfig::store_file`]
+    pub fn dictionary_file(&self) -> Option<PathBuf> {
+        non_empty_path(&self.path)
+    }
+}
+
+/// Every invalid setting found when loading config
+#[derive(Debug)]
+pub struct ConfigError(pub Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid config:")?;
+        for error in &self.0 {
+            write!(f, "\n  - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Where config is read from
+#[derive(Clone)]
+pub struct ConfigSource {
+    pub path: PathBuf,
+    /// Whether the file must exist, i.e., it is given by `--config`
+    required: bool,
+}
+
+impl ConfigSource {
+    /// Parse `--config <path>` or `--config=<path>` from command line
+    /// `args`, excluding program name
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        let mut source = Self {
+            path: PathBuf::from(DEFAULT_CONFIG_PATH),
+            required: false,
         };
-        config
+        let mut errors = vec![];
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let path = match arg.strip_prefix("--config") {
+                Some("") => args.next(),
+                Some(path) if path.starts_with('=') => Some(path[1..].to_string()),
+                _ => {
+                    errors.push(format!("Unknown argument {}", arg));
+                    continue;
+                }
+            };
+            match path {
+                Some(path) if !path.is_empty() => {
+                    source.path = PathBuf::from(path);
+                    source.required = true;
+                }
+                _ => errors.push("--config requires a path".to_string()),
+            }
+        }
+        if errors.is_empty() {
+            Ok(source)
+        } else {
+            Err(ConfigError(errors))
+        }
+    }
+
+    /// Read config file, apply overrides of `UDIAB_*` env vars in `vars`,
+    /// and validate the result
+    ///
+    /// Absent settings take their defaults. All unknown or mistyped
+    /// settings are reported at once, and so are settings out of range
+    /// once types are right.
+    pub fn load(
+        &self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Config, ConfigError> {
+        let mut errors = vec![];
+        let defaults = Value::try_from(Config::default()).expect("Default config is serializable");
+
+        let mut value = Value::Table(Default::default());
+        match fs::read_to_string(&self.path) {
+            Ok(config_str) => match config_str.parse::<Value>() {
+                Ok(file_value) => value = file_value,
+                Err(error) => errors.push(format!("{}: {}", self.path.display(), error)),
+            },
+            Err(error) if self.required || self.path.exists() => {
+                errors.push(format!("{}: {}", self.path.display(), error))
+            }
+            Err(_) => {}
+        }
+        check_value(&value, &defaults, "", &mut errors);
+
+        let mut api_keys = vec![];
+        for (name, raw) in vars {
+            if name == API_KEYS_ENV {
+                for api_key in raw.split(',').filter(|api_key| !api_key.is_empty()) {
+                    match api_key.parse::<ApiKeyConfig>() {
+                        Ok(api_key) => api_keys.push(api_key),
+                        Err(error) => errors.push(format!("{}: {}", name, error)),
+                    }
+                }
+                continue;
+            }
+            let path = match name.strip_prefix(ENV_PREFIX) {
+                Some(path) => path
+                    .split(ENV_SECTION_SEPARATOR)
+                    .map(str::to_lowercase)
+                    .collect::<Vec<_>>(),
+                None => continue,
+            };
+            let default = match lookup(&defaults, &path) {
+                Some(default) => default,
+                None => {
+                    errors.push(format!("{}: unknown setting", name));
+                    continue;
+                }
+            };
+            let env_value = parse_env_value(&raw, default);
+            check_value(&env_value, default, &name, &mut errors);
+            insert(&mut value, &path, env_value);
+        }
+
+        if !errors.is_empty() {
+            return Err(ConfigError(errors));
+        }
+        let mut config: Config = value
+            .try_into()
+            .map_err(|error| ConfigError(vec![error.to_string()]))?;
+        config.auth.api_keys.extend(api_keys);
+        let errors = config.validate();
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError(errors))
+        }
+    }
+}
+
+impl Config {
+    /// Retrieve config from file given by `--config` (./backend-config.toml
+    /// by default) with overrides of `UDIAB_*` env vars, together with the
+    /// source for reloading
+    pub fn retrieve_config() -> Result<(Self, ConfigSource), ConfigError> {
+        let source = ConfigSource::from_args(std::env::args().skip(1))?;
+        let config = source.load(std::env::vars())?;
+        Ok((config, source))
+    }
+
+    /// Parsed `log_level`, which is valid once config is loaded
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level.parse().unwrap_or(LevelFilter::Info)
+    }
+
+    /// Errors of settings out of range
+    fn validate(&self) -> Vec<String> {
+        let mut errors = vec![];
+        let mut check = |valid: bool, name: &str, reason: &str| {
+            if !valid {
+                errors.push(format!("{}: {}", name, reason));
+            }
+        };
+        check(!self.host.is_empty(), "host", "must not be empty");
+        check(
+            self.log_level.parse::<LevelFilter>().is_ok(),
+            "log_level",
+            "must be one of off, error, warn, info, debug and trace",
+        );
+        check(
+            self.popularity.write_back_interval > 0,
+            "popularity.write_back_interval",
+            "must be positive",
+        );
+        check(
+            self.synonyms.boost.is_finite() && self.synonyms.boost > 0.0,
+            "synonyms.boost",
+            "must be positive",
+        );
+        check(
+            self.limits.max_page_size > 0,
+            "limits.max_page_size",
+            "must be positive",
+        );
+        check(
+            self.search_pool.threads > 0,
+            "search_pool.threads",
+            "must be positive",
+        );
+        check(
+            self.search_pool.timeout > 0,
+            "search_pool.timeout",
+            "must be positive",
+        );
+        check(
+            self.rate_limit.requests_per_second.is_finite()
+                && self.rate_limit.requests_per_second >= 0.0,
+            "rate_limit.requests_per_second",
+            "must be non-negative",
+        );
+        check(
+            self.rate_limit.requests_per_second == 0.0 || self.rate_limit.burst >= 1.0,
+            "rate_limit.burst",
+            "must be at least 1",
+        );
+        check(
+            self.rate_limit.max_concurrent_exports > 0,
+            "rate_limit.max_concurrent_exports",
+            "must be positive",
+        );
+        check(
+            self.rate_limit.clicks_per_second.is_finite()
+                && self.rate_limit.clicks_per_second >= 0.0,
+            "rate_limit.clicks_per_second",
+            "must be non-negative",
+        );
+        check(
+            self.rate_limit.clicks_per_second == 0.0 || self.rate_limit.click_burst >= 1.0,
+            "rate_limit.click_burst",
+            "must be at least 1",
+        );
+        for api_key in &self.auth.api_keys {
+            check(
+                !api_key.key.is_empty(),
+                "auth.api_keys",
+                "key must not be empty",
+            );
+        }
+        for origin in &self.http.cors_allowed_origins {
+            check(
+                origin == "*" || origin.starts_with("http://") || origin.starts_with("https://"),
+                "http.cors_allowed_origins",
+                &format!("{} is neither * nor an http(s) origin", origin),
+            );
+        }
+        if self.tls.enabled {
+            check(
+                !self.tls.cert_path.is_empty(),
+                "tls.cert_path",
+                "must not be empty",
+            );
+            check(
+                !self.tls.key_path.is_empty(),
+                "tls.key_path",
+                "must not be empty",
+            );
+            check(
+                self.tls.reload_interval > 0,
+                "tls.reload_interval",
+                "must be positive",
+            );
+            check(
+                !self.tls.redirect_http || self.tls.redirect_port != self.port,
+                "tls.redirect_port",
+                "must differ from port",
+            );
+        }
+        errors
     }
 }
+
+/// Check that every setting in `value` is known and of the same type as
+/// in `default`, appending errors otherwise
+///
+/// Settings absent by default (e.g., `None` options) are unknown.
+fn check_value(value: &Value, default: &Value, name: &str, errors: &mut Vec<String>) {
+    match (value, default) {
+        (Value::Table(table), Value::Table(default_table)) => {
+            for (key, value) in table {
+                let name = if name.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", name, key)
+                };
+                match default_table.get(key) {
+                    Some(default) => check_value(value, default, &name, errors),
+                    None => errors.push(format!("{}: unknown setting", name)),
+                }
+            }
+        }
+        (Value::Integer(_), Value::Float(_)) => {}
+        (value, default) if value.same_type(default) => {}
+        (value, default) => errors.push(format!(
+            "{}: expected {}, found {}",
+            name,
+            default.type_str(),
+            value.type_str()
+        )),
+    }
+}
+
+/// Setting at `path` of `value`
+fn lookup<'v>(value: &'v Value, path: &[String]) -> Option<&'v Value> {
+    path.iter().try_fold(value, |value, key| value.get(key))
+}
+
+/// Set setting at `path` of `value`, creating tables on the way
+fn insert(value: &mut Value, path: &[String], setting: Value) {
+    let (key, sections) = match path.split_last() {
+        Some(split) => split,
+        None => return,
+    };
+    let mut table = value;
+    for section in sections {
+        if let Value::Table(current) = table {
+            table = current
+                .entry(section.clone())
+                .or_insert_with(|| Value::Table(Default::default()));
+        }
+    }
+    if let Value::Table(table) = table {
+        table.insert(key.clone(), setting);
+    }
+}
+
+/// Parse env var as a TOML value of the type of `default`
+///
+/// String settings take the raw value. Others are parsed as TOML, e.g.,
+/// `8`, `true` or `["https://a.com"]`, and fall back to a string.
+fn parse_env_value(raw: &str, default: &Value) -> Value {
+    if default.is_str() {
+        return Value::String(raw.to_string());
+    }
+    format!("value = {}", raw)
+        .parse::<Value>()
+        .ok()
+        .and_then(|mut table| table.as_table_mut()?.remove("value"))
+        .unwrap_or_else(|| Value::String(raw.to_string()))
+}
+
+/// Config of query result caches
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// Max count of entries in each cache, 0 to disable caching
+    pub capacity: usize,
+    /// Seconds before a cached entry expires
+    pub ttl: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            ttl: 60,
+        }
+    }
+}
+
+/// Maxima of request parameters, requests exceeding which are rejected
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LimitsConfig {
+    pub max_page_size: usize,
+    /// Max offset of offset pagination. Deeper pages should be fetched by cursor
+    pub max_offset: usize,
+    /// Max length of key (in UTF-8 chars)
+    pub max_key_length: usize,
+    /// Max complexity of regex key, see [`crate::validation::regex_complexity`]
+    pub max_regex_complexity: usize,
+    /// Max count of clauses in a query DSL
+    pub max_clause_count: usize,
+    /// Whether regex keys must start with a literal prefix, e.g., `abc.*`
+    /// instead of `.*abc`, which would walk the whole term dictionary
+    pub require_regex_literal_prefix: bool,
+    /// Max size (in bytes) of the DFA a regex key is compiled into
+    pub max_regex_automaton_size: usize,
+    /// Max count of terms a regex key is expanded into. If exceeded, results
+    /// are partial
+    pub max_regex_expanded_terms: usize,
+    /// Milliseconds allowed for expanding a regex key. If exceeded, results
+    /// are partial
+    pub regex_time_budget: u64,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            max_page_size: 100,
+            max_offset: 10000,
+            max_key_length: 256,
+            max_regex_complexity: 1000,
+            max_clause_count: 64,
+            require_regex_literal_prefix: true,
+            max_regex_automaton_size: 1 << 20,
+            max_regex_expanded_terms: 1024,
+            regex_time_budget: 200,
+        }
+    }
+}
+
+/// Config of thread pool running searches
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SearchPoolConfig {
+    /// Count of search threads, defaults to count of CPUs
+    pub threads: usize,
+    /// Max count of searches waiting for a thread, beyond which searches are
+    /// rejected with 503
+    pub queue_size: usize,
+    /// Milliseconds before a search is stopped and answered with 504
+    pub timeout: u64,
+}
+
+impl Default for SearchPoolConfig {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(4, |threads| threads.get()),
+            queue_size: 256,
+            timeout: 5000,
+        }
+    }
+}
+
+/// Config of rate limiting and concurrency control
+///
+/// Rate limiting is on by default and identifies clients by the peer IP.
+/// Behind a reverse proxy that IP is the proxy's, so that all clients share
+/// one bucket unless `trust_forwarded_for` is set.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Tokens refilled per second to each client, 0 to disable rate limiting
+    pub requests_per_second: f64,
+    /// Max tokens of each client, i.e., max count of requests in a burst
+    pub burst: f64,
+    /// Max count of clients remembered, beyond which least recently seen
+    /// clients are forgotten
+    pub max_clients: usize,
+    /// Whether to identify clients by `X-Forwarded-For` and `Forwarded`
+    /// headers. Must be enabled behind a reverse proxy, and only behind a
+    /// trusted one, since clients can forge these headers otherwise
+    pub trust_forwarded_for: bool,
+    /// Max count of Regex, Complex and MoreLikeThis searches running
+    /// concurrently, 0 for unlimited
+    pub max_concurrent_expensive_queries: usize,
+    /// Max count of exports streaming concurrently, each of which takes a
+    /// thread of its own until its client finishes reading
+    pub max_concurrent_exports: usize,
+    /// Tokens refilled per second to each client for `/click`, which has
+    /// buckets of its own, 0 to disable rate limiting of clicks
+    pub clicks_per_second: f64,
+    /// Max tokens of each client for `/click`
+    pub click_burst: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 10.0,
+            burst: 20.0,
+            max_clients: 10000,
+            trust_forwarded_for: false,
+            max_concurrent_expensive_queries: 8,
+            max_concurrent_exports: 4,
+            clicks_per_second: 1.0,
+            click_burst: 10.0,
+        }
+    }
+}
+
+/// Config of API key authentication
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Known API keys. More keys can be given by env var `UDIAB_API_KEYS`
+    /// as comma separated `key:role` pairs
+    pub api_keys: Vec<ApiKeyConfig>,
+    /// Whether search endpoints require an API key as well. Endpoints under
+    /// `/api/admin` always do
+    pub require_read_key: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    pub role: Role,
+}
+
+impl FromStr for ApiKeyConfig {
+    type Err = String;
+
+    /// Parse from `key:role`
+    fn from_str(api_key: &str) -> Result<Self, Self::Err> {
+        let (key, role) = api_key
+            .rsplit_once(':')
+            .ok_or_else(|| "API key should be in format of key:role.".to_string())?;
+        Ok(Self {
+            key: key.to_string(),
+            role: role.parse()?,
+        })
+    }
+}
+
+/// Config of CORS, compression and HTTP caching
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// Origins allowed to call API cross-origin, e.g.,
+    /// `https://udiab.example.com`, or `*` for any origin. If empty, only
+    /// same-origin calls are allowed
+    pub cors_allowed_origins: Vec<String>,
+    /// Seconds for which browsers may cache CORS preflight results
+    pub cors_max_age: usize,
+    /// Whether to compress responses with gzip or brotli, as accepted by
+    /// client
+    pub compress: bool,
+    /// Seconds for which clients may cache responses of `/top_info` and
+    /// `/more_like_this`, 0 to disable HTTP caching
+    pub cache_max_age: u64,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            cors_allowed_origins: vec![],
+            cors_max_age: 3600,
+            compress: true,
+            cache_max_age: 60,
+        }
+    }
+}
+
+/// Config of HTTPS
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// Whether to serve HTTPS instead of HTTP on `port`
+    pub enabled: bool,
+    /// Path of PEM certificate chain
+    pub cert_path: String,
+    /// Path of PEM private key, either PKCS#8 or RSA
+    pub key_path: String,
+    /// Seconds between checks of certificate and key files, which are
+    /// reloaded once changed
+    pub reload_interval: u64,
+    /// Whether to redirect plain HTTP on `redirect_port` to HTTPS
+    pub redirect_http: bool,
+    pub redirect_port: u16,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_path: "./cert.pem".to_string(),
+            key_path: "./key.pem".to_string(),
+            reload_interval: 60,
+            redirect_http: false,
+            redirect_port: 80,
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./config_test.rs"]
+mod config_test;
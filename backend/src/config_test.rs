@@ -0,0 +1,157 @@
+use super::*;
+use std::io::Write;
+
+fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+    vars.iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+fn write_config(config_str: &str) -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(config_str.as_bytes()).unwrap();
+    file
+}
+
+#[test]
+fn test_from_args() {
+    let source = ConfigSource::from_args(vec![]).unwrap();
+    assert_eq!(source.path, PathBuf::from(DEFAULT_CONFIG_PATH));
+    assert!(!source.required);
+
+    for args in [
+        vec!["--config", "/etc/udiab.toml"],
+        vec!["--config=/etc/udiab.toml"],
+    ] {
+        let source = ConfigSource::from_args(args.into_iter().map(String::from)).unwrap();
+        assert_eq!(source.path, PathBuf::from("/etc/udiab.toml"));
+        assert!(source.required);
+    }
+
+    let got = ConfigSource::from_args(vec!["--verbose".to_string(), "--config".to_string()]);
+    assert_eq!(got.err().unwrap().0.len(), 2);
+}
+
+#[test]
+fn test_load_defaults_without_file() {
+    let source = ConfigSource {
+        path: PathBuf::from("./not-exist.toml"),
+        required: false,
+    };
+    let config = source.load(vec![]).unwrap();
+    assert_eq!(config.port, 80);
+    assert_eq!(config.search_pool.queue_size, 256);
+
+    let source = ConfigSource {
+        required: true,
+        ..source
+    };
+    assert!(source.load(vec![]).is_err());
+}
+
+#[test]
+fn test_load_with_env_overrides() {
+    let file = write_config(
+        r#"
+        host = "127.0.0.1"
+        port = 8080
+        index_store_directory = "./index"
+
+        [cache]
+        capacity = 16
+
+        [popularity]
+        store_path = ""
+        "#,
+    );
+    let source = ConfigSource {
+        path: file.path().to_path_buf(),
+        required: true,
+    };
+    let config = source
+        .load(vars(&[
+            ("UDIAB_PORT", "9090"),
+            ("UDIAB_HOST", "::1"),
+            ("UDIAB_CACHE__TTL", "5"),
+            ("UDIAB_SEARCH_POOL__THREADS", "2"),
+            ("UDIAB_RATE_LIMIT__REQUESTS_PER_SECOND", "3"),
+            ("UDIAB_HTTP__CORS_ALLOWED_ORIGINS", r#"["https://a.com"]"#),
+            ("UDIAB_API_KEYS", "root:admin"),
+            ("PATH", "/usr/bin"),
+        ]))
+        .unwrap();
+    assert_eq!(config.host, "::1");
+    assert_eq!(config.port, 9090);
+    assert_eq!(config.cache.capacity, 16);
+    assert_eq!(config.cache.ttl, 5);
+    assert_eq!(config.search_pool.threads, 2);
+    assert_eq!(config.rate_limit.requests_per_second, 3.0);
+    assert_eq!(config.http.cors_allowed_origins, vec!["https://a.com"]);
+    assert_eq!(config.auth.api_keys.len(), 1);
+    assert!(config.popularity.store_file().is_none());
+    assert_eq!(
+        config.synonyms.dictionary_file(),
+        Some(PathBuf::from("./synonyms.txt"))
+    );
+}
+
+#[test]
+fn test_load_reports_all_errors() {
+    let file = write_config(
+        r#"
+        port = "eighty"
+        unknown = 1
+
+        [limits]
+        max_page_size = 0
+        "#,
+    );
+    let source = ConfigSource {
+        path: file.path().to_path_buf(),
+        required: true,
+    };
+    let errors = source
+        .load(vars(&[
+            ("UDIAB_CACHE__CAPACITY", "many"),
+            ("UDIAB_CACHE__SIZE", "1"),
+            ("UDIAB_API_KEYS", "root"),
+        ]))
+        .err()
+        .unwrap()
+        .0;
+    assert_eq!(errors.len(), 5, "{:?}", errors);
+    for name in [
+        "port",
+        "unknown",
+        "UDIAB_CACHE__CAPACITY",
+        "UDIAB_CACHE__SIZE",
+        "UDIAB_API_KEYS",
+    ] {
+        assert!(
+            errors.iter().any(|error| error.starts_with(name)),
+            "{} not in {:?}",
+            name,
+            errors
+        );
+    }
+
+    let errors = ConfigSource {
+        path: PathBuf::from("./not-exist.toml"),
+        required: false,
+    }
+    .load(vars(&[
+        ("UDIAB_LIMITS__MAX_PAGE_SIZE", "0"),
+        ("UDIAB_SEARCH_POOL__THREADS", "0"),
+        ("UDIAB_HTTP__CORS_ALLOWED_ORIGINS", r#"["a.com"]"#),
+        ("UDIAB_LOG_LEVEL", "verbose"),
+        ("UDIAB_TLS__ENABLED", "true"),
+        ("UDIAB_TLS__RELOAD_INTERVAL", "0"),
+    ]))
+    .err()
+    .unwrap()
+    .0;
+    assert_eq!(errors.len(), 5, "{:?}", errors);
+    assert!(errors
+        .iter()
+        .any(|error| error.starts_with("tls.reload_interval")));
+}
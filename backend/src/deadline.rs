@@ -0,0 +1,230 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tantivy::{
+    collector::{Collector, SegmentCollector},
+    query::Weight,
+    DocId, DocSet, Score, SegmentOrdinal, SegmentReader, TERMINATED,
+};
+
+thread_local! {
+    /// Deadline of the search running on this thread
+    static CURRENT_DEADLINE: RefCell<Option<Arc<Deadline>>> = const { RefCell::new(None) };
+}
+
+/// Deadline of a search, which expires once time is up or the search is
+/// cancelled (e.g., client disconnected).
+///
+/// Long running steps check the deadline of current thread (see
+/// [`Deadline::scope`]) and stop early, leaving partial results.
+pub struct Deadline {
+    expires_at: Instant,
+    cancelled: AtomicBool,
+}
+
+impl Deadline {
+    pub fn new(timeout: Duration) -> Arc<Self> {
+        Arc::new(Self {
+            expires_at: Instant::now() + timeout,
+            cancelled: AtomicBool::new(false),
+        })
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || Instant::now() >= self.expires_at
+    }
+
+    /// Time left before expiring
+    pub fn remaining(&self) -> Duration {
+        self.expires_at.saturating_duration_since(Instant::now())
+    }
+
+    /// Run `f` with this deadline as the deadline of current thread
+    pub fn scope<R>(self: &Arc<Self>, f: impl FnOnce() -> R) -> R {
+        let previous = CURRENT_DEADLINE.with(|current| current.borrow_mut().replace(self.clone()));
+        let result = f();
+        CURRENT_DEADLINE.with(|current| *current.borrow_mut() = previous);
+        result
+    }
+
+    /// Deadline of current thread, if any
+    pub fn current() -> Option<Arc<Deadline>> {
+        CURRENT_DEADLINE.with(|current| current.borrow().clone())
+    }
+
+    /// Whether the deadline of current thread has expired.
+    ///
+    /// Always `false` if there is no deadline.
+    pub fn current_expired() -> bool {
+        CURRENT_DEADLINE.with(|current| {
+            current
+                .borrow()
+                .as_ref()
+                .is_some_and(|deadline| deadline.is_expired())
+        })
+    }
+}
+
+/// Cancels deadline when dropped, unless disarmed
+///
+/// Actix drops the handler future once client disconnects, so holding this
+/// guard across awaits stops the search on disconnection.
+pub struct CancelOnDrop {
+    deadline: Arc<Deadline>,
+    armed: bool,
+}
+
+impl CancelOnDrop {
+    pub fn new(deadline: Arc<Deadline>) -> Self {
+        Self {
+            deadline,
+            armed: true,
+        }
+    }
+
+    pub fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if self.armed {
+            self.deadline.cancel();
+        }
+    }
+}
+
+/// Docs collected between two checks of deadline
+const DOCS_PER_DEADLINE_CHECK: u32 = 64;
+
+/// Collector which stops collecting once deadline of current thread expires
+///
+/// Segments starting after the deadline are skipped. Within a segment, the
+/// scan is either driven by this collector, checking the deadline every
+/// [`DOCS_PER_DEADLINE_CHECK`] docs (see [`with_deadline`]), or left to the
+/// wrapped collector (see [`with_segment_deadline`]).
+pub struct DeadlineCollector<C> {
+    inner: C,
+    deadline: Option<Arc<Deadline>>,
+    drive_scan: bool,
+}
+
+/// Wrap collector to respect deadline of current thread, also within a
+/// segment
+///
+/// Meant for collectors visiting every matching doc (e.g., counts and
+/// facets). Driving the scan gives up specialized scans of the wrapped
+/// collector, such as block-max pruning of `TopDocs`.
+pub fn with_deadline<C: Collector>(collector: C) -> DeadlineCollector<C> {
+    DeadlineCollector {
+        inner: collector,
+        deadline: Deadline::current(),
+        drive_scan: true,
+    }
+}
+
+/// Wrap collector to respect deadline of current thread between segments
+/// only
+///
+/// Meant for `TopDocs` ranked by relevance, whose block-max pruning skips
+/// most docs anyway, so a segment is scanned in full once started.
+pub fn with_segment_deadline<C: Collector>(collector: C) -> DeadlineCollector<C> {
+    DeadlineCollector {
+        inner: collector,
+        deadline: Deadline::current(),
+        drive_scan: false,
+    }
+}
+
+pub struct DeadlineSegmentCollector<C> {
+    inner: C,
+    deadline: Option<Arc<Deadline>>,
+    collected: u32,
+    expired: bool,
+}
+
+impl<C: Collector> Collector for DeadlineCollector<C> {
+    type Fruit = C::Fruit;
+    type Child = DeadlineSegmentCollector<C::Child>;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(DeadlineSegmentCollector {
+            inner: self.inner.for_segment(segment_ord, segment_reader)?,
+            deadline: self.deadline.clone(),
+            collected: 0,
+            expired: false,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.inner.requires_scoring()
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> tantivy::Result<<Self::Child as SegmentCollector>::Fruit> {
+        if self
+            .deadline
+            .as_ref()
+            .is_some_and(|deadline| deadline.is_expired())
+        {
+            return Ok(self.for_segment(segment_ord, reader)?.harvest());
+        }
+        if !self.drive_scan {
+            return self.inner.collect_segment(weight, segment_ord, reader);
+        }
+        let mut segment_collector = self.for_segment(segment_ord, reader)?;
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let delete_bitset = reader.delete_bitset();
+        let mut doc = scorer.doc();
+        while doc != TERMINATED && !segment_collector.expired {
+            if delete_bitset.is_none_or(|delete_bitset| delete_bitset.is_alive(doc)) {
+                segment_collector.collect(doc, scorer.score());
+            }
+            doc = scorer.advance();
+        }
+        Ok(segment_collector.harvest())
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        self.inner.merge_fruits(segment_fruits)
+    }
+}
+
+impl<C: SegmentCollector> SegmentCollector for DeadlineSegmentCollector<C> {
+    type Fruit = C::Fruit;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        if self.expired {
+            return;
+        }
+        if let Some(deadline) = &self.deadline {
+            self.collected = self.collected.wrapping_add(1);
+            if self.collected.is_multiple_of(DOCS_PER_DEADLINE_CHECK) && deadline.is_expired() {
+                self.expired = true;
+                return;
+            }
+        }
+        self.inner.collect(doc, score);
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.inner.harvest()
+    }
+}
@@ -0,0 +1,278 @@
+use super::interfaces::{ExportFormat, ExportedArticle, UserError};
+use actix_web::web::Bytes;
+use futures::channel::mpsc::Sender;
+use futures::executor::block_on;
+use futures::SinkExt;
+use search_base::ProjectDocument;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tantivy::{
+    collector::{Collector, SegmentCollector},
+    fastfield::FacetReader,
+    query::{Query, Weight},
+    schema::Facet,
+    store::StoreReader,
+    DocId, Score, Searcher, SegmentOrdinal, SegmentReader,
+};
+
+/// Count of exported articles buffered before the client reads them
+pub const EXPORT_BUFFER_SIZE: usize = 64;
+
+/// Exported articles, or the error stopping export
+pub type ExportSender = Sender<Result<ExportedArticle, UserError>>;
+
+/// Collector sending every hit to an [`ExportSender`] as soon as it is
+/// collected, so that hits are never gathered in memory
+///
+/// Sending blocks while the buffer is full, i.e., export proceeds at the
+/// pace of the client. Once the client is gone or export is cancelled,
+/// remaining hits are skipped.
+struct ExportCollector {
+    project_document: ProjectDocument,
+    include_body: bool,
+    sender: ExportSender,
+    stopped: Arc<AtomicBool>,
+}
+
+struct ExportSegmentCollector {
+    project_document: ProjectDocument,
+    include_body: bool,
+    sender: ExportSender,
+    stopped: Arc<AtomicBool>,
+    store_reader: StoreReader,
+    source_reader: FacetReader,
+    /// Buffer of facet ordinals of a doc
+    source_ords: Vec<u64>,
+}
+
+impl Collector for ExportCollector {
+    type Fruit = ();
+    type Child = ExportSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(ExportSegmentCollector {
+            project_document: self.project_document,
+            include_body: self.include_body,
+            sender: self.sender.clone(),
+            stopped: self.stopped.clone(),
+            store_reader: segment_reader.get_store_reader()?,
+            source_reader: segment_reader.facet_reader(self.project_document.source)?,
+            source_ords: vec![],
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, _segment_fruits: Vec<()>) -> tantivy::Result<()> {
+        Ok(())
+    }
+}
+
+impl ExportSegmentCollector {
+    fn exported_article(&mut self, doc: DocId) -> Result<ExportedArticle, UserError> {
+        let ProjectDocument {
+            title: title_field,
+            body: body_field,
+            url: url_field,
+            likes: likes_field,
+            time: time_field,
+            ..
+        } = self.project_document;
+        let document = self
+            .store_reader
+            .get(doc)
+            .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
+        let mut title = None;
+        let mut url = None;
+        let mut body = None;
+        let mut likes = None;
+        let mut time = None;
+        for field_value in document.field_values() {
+            match field_value.field() {
+                field if field == title_field => title = field_value.value().text(),
+                field if field == url_field => url = field_value.value().text(),
+                field if field == body_field => body = field_value.value().text(),
+                field if field == likes_field => likes = field_value.value().u64_value(),
+                field if field == time_field => time = field_value.value().date_value(),
+                _ => {}
+            }
+        }
+        self.source_ords.clear();
+        self.source_reader.facet_ords(doc, &mut self.source_ords);
+        let mut source = Facet::root();
+        if let Some(&source_ord) = self.source_ords.first() {
+            self.source_reader
+                .facet_from_ord(source_ord, &mut source)
+                .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
+        }
+        let missing = |field: &str| UserError::Unexpected(format!("Can't find {} field", field));
+        Ok(ExportedArticle {
+            url: url.ok_or_else(|| missing("url"))?.to_string(),
+            title: title.ok_or_else(|| missing("title"))?.to_string(),
+            time: *time.ok_or_else(|| missing("time"))?,
+            likes: likes.ok_or_else(|| missing("likes"))?,
+            source: source.to_path().join("/"),
+            body: if self.include_body {
+                Some(body.ok_or_else(|| missing("body"))?.to_string())
+            } else {
+                None
+            },
+        })
+    }
+}
+
+impl SegmentCollector for ExportSegmentCollector {
+    type Fruit = ();
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        if self.stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        let exported_article = self.exported_article(doc);
+        let failed = exported_article.is_err();
+        // Send fails only if client is gone
+        if block_on(self.sender.send(exported_article)).is_err() || failed {
+            self.stopped.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {}
+}
+
+/// Export query prepared by [`crate::model::UdiabModel::prepare_export`]
+///
+/// Only segment readers are kept instead of the searcher, so that a long
+/// export does not hold a searcher of the pool shared with searches.
+pub struct PreparedExport {
+    weight: Box<dyn Weight>,
+    segment_readers: Vec<SegmentReader>,
+    project_document: ProjectDocument,
+    include_body: bool,
+    /// Whether some matches may be missing, since regex expansion hit its
+    /// limit of terms or time
+    pub partial: bool,
+}
+
+impl PreparedExport {
+    pub fn new(
+        searcher: &Searcher,
+        query: &dyn Query,
+        project_document: ProjectDocument,
+        include_body: bool,
+        partial: bool,
+    ) -> Result<Self, UserError> {
+        Ok(Self {
+            weight: query
+                .weight(searcher, false)
+                .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?,
+            segment_readers: searcher.segment_readers().to_vec(),
+            project_document,
+            include_body,
+            partial,
+        })
+    }
+
+    /// Send every matched article to `sender`, segment by segment
+    ///
+    /// Blocks until all are sent, the receiver is dropped or `cancelled` is
+    /// set, so this should run on a thread of its own.
+    pub fn run(self, sender: ExportSender, cancelled: Arc<AtomicBool>) {
+        let collector = ExportCollector {
+            project_document: self.project_document,
+            include_body: self.include_body,
+            sender: sender.clone(),
+            stopped: cancelled,
+        };
+        for (segment_ord, segment_reader) in self.segment_readers.iter().enumerate() {
+            if collector.stopped.load(Ordering::Relaxed) {
+                return;
+            }
+            if let Err(tantivy_error) =
+                collector.collect_segment(&*self.weight, segment_ord as u32, segment_reader)
+            {
+                let mut sender = sender;
+                let _ = block_on(sender.send(Err(UserError::UnexpectedTantivy { tantivy_error })));
+                return;
+            }
+        }
+    }
+}
+
+/// Content type of `format`
+pub fn content_type(format: ExportFormat) -> &'static str {
+    match format {
+        ExportFormat::Csv => "text/csv; charset=utf-8",
+        ExportFormat::Jsonl => "application/x-ndjson",
+    }
+}
+
+/// Bytes preceding exported articles, i.e., header row of CSV
+pub fn header(format: ExportFormat, include_body: bool) -> Bytes {
+    match format {
+        ExportFormat::Csv if include_body => {
+            Bytes::from_static(b"url,title,time,likes,source,body\r\n")
+        }
+        ExportFormat::Csv => Bytes::from_static(b"url,title,time,likes,source\r\n"),
+        ExportFormat::Jsonl => Bytes::new(),
+    }
+}
+
+/// Encode an exported article as a line of `format`
+pub fn encode(format: ExportFormat, exported_article: &ExportedArticle) -> Bytes {
+    match format {
+        ExportFormat::Csv => {
+            let mut fields = vec![
+                csv_field(&exported_article.url),
+                csv_field(&exported_article.title),
+                exported_article.time.timestamp_millis().to_string(),
+                exported_article.likes.to_string(),
+                csv_field(&exported_article.source),
+            ];
+            if let Some(body) = &exported_article.body {
+                fields.push(csv_field(body));
+            }
+            let mut line = fields.join(",");
+            line.push_str("\r\n");
+            Bytes::from(line)
+        }
+        ExportFormat::Jsonl => {
+            let mut line = serde_json::to_vec(exported_article).unwrap_or_default();
+            line.push(b'\n');
+            Bytes::from(line)
+        }
+    }
+}
+
+/// Quote CSV field as RFC 4180 if it contains separators, quotes or line
+/// breaks
+///
+/// Fields starting like a formula (e.g., `=HYPERLINK(...)` in a scraped
+/// title) are prefixed by `'`, so that spreadsheets show them as text
+/// instead of running them. Leading whitespace is skipped and full-width
+/// signs count as well, since spreadsheets accept both.
+fn csv_field(field: &str) -> String {
+    let formula = field.starts_with(['\t', '\r'])
+        || field
+            .trim_start()
+            .starts_with(['=', '+', '-', '@', '＝', '＋', '－', '＠']);
+    let field = if formula {
+        format!("'{}", field)
+    } else {
+        field.to_string()
+    };
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+#[cfg(test)]
+#[path = "./export_test.rs"]
+mod export_test;
@@ -0,0 +1,69 @@
+use super::*;
+use chrono::{TimeZone, Utc};
+
+fn exported_article(body: Option<&str>) -> ExportedArticle {
+    ExportedArticle {
+        url: "https://a.com/1".to_string(),
+        title: "Say \"hi\", world".to_string(),
+        time: Utc.timestamp_millis(1234),
+        likes: 5,
+        source: "a.com".to_string(),
+        body: body.map(str::to_string),
+    }
+}
+
+#[test]
+fn test_encode_csv() {
+    assert_eq!(
+        header(ExportFormat::Csv, false),
+        "url,title,time,likes,source\r\n"
+    );
+    assert_eq!(
+        encode(ExportFormat::Csv, &exported_article(None)),
+        "https://a.com/1,\"Say \"\"hi\"\", world\",1234,5,a.com\r\n"
+    );
+    assert_eq!(
+        encode(ExportFormat::Csv, &exported_article(Some("line\nbreak"))),
+        "https://a.com/1,\"Say \"\"hi\"\", world\",1234,5,a.com,\"line\nbreak\"\r\n"
+    );
+}
+
+#[test]
+fn test_csv_field_formula() {
+    assert_eq!(csv_field("=1+1"), "'=1+1");
+    assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+    assert_eq!(csv_field("-2"), "'-2");
+    assert_eq!(
+        csv_field("=HYPERLINK(\"x\",\"y\")"),
+        "\"'=HYPERLINK(\"\"x\"\",\"\"y\"\")\""
+    );
+    assert_eq!(csv_field(" =1+1"), "' =1+1");
+    assert_eq!(csv_field("\u{3000}@SUM(A1)"), "'\u{3000}@SUM(A1)");
+    assert_eq!(csv_field("＝1+1"), "'＝1+1");
+    assert_eq!(csv_field("\t1"), "'\t1");
+    assert_eq!(csv_field("a=1"), "a=1");
+    assert_eq!(csv_field(" a"), " a");
+}
+
+#[test]
+fn test_encode_jsonl() {
+    assert!(header(ExportFormat::Jsonl, true).is_empty());
+    let line = encode(ExportFormat::Jsonl, &exported_article(Some("line\nbreak")));
+    assert!(line.ends_with(b"\n"));
+    assert_eq!(line.iter().filter(|byte| **byte == b'\n').count(), 1);
+    let got: serde_json::Value = serde_json::from_slice(&line).unwrap();
+    assert_eq!(
+        got,
+        serde_json::json!({
+            "url": "https://a.com/1",
+            "title": "Say \"hi\", world",
+            "time": 1234,
+            "likes": 5,
+            "source": "a.com",
+            "body": "line\nbreak"
+        })
+    );
+    let line = encode(ExportFormat::Jsonl, &exported_article(None));
+    let got: serde_json::Value = serde_json::from_slice(&line).unwrap();
+    assert!(got.get("body").is_none());
+}
@@ -0,0 +1,55 @@
+use actix_web::{
+    dev::HttpResponseBuilder,
+    http::header::{CacheControl, CacheDirective, EntityTag, IfNoneMatch, ETAG},
+    HttpMessage, HttpRequest,
+};
+use chrono::Utc;
+
+/// HTTP caching of responses which only change with searcher generation,
+/// e.g., `/top_info` and `/more_like_this`
+///
+/// Responses of rolling top info windows change with time as well, so the
+/// tag also changes every `max_age` seconds, which matches how long such
+/// results are cached in model anyway.
+pub struct HttpCache {
+    etag: EntityTag,
+    max_age: u64,
+}
+
+impl HttpCache {
+    /// HTTP caching of response computed by searcher of `generation`, `None`
+    /// if `max_age` is 0
+    pub fn new(generation: u64, max_age: u64) -> Option<Self> {
+        if max_age == 0 {
+            return None;
+        }
+        let slot = Utc::now().timestamp().max(0) as u64 / max_age;
+        Some(Self {
+            etag: EntityTag::strong(format!("{:x}-{:x}", generation, slot)),
+            max_age,
+        })
+    }
+
+    /// Whether client sending `request` already has a fresh response
+    pub fn is_fresh(&self, request: &HttpRequest) -> bool {
+        match request.get_header::<IfNoneMatch>() {
+            Some(IfNoneMatch::Any) => true,
+            Some(IfNoneMatch::Items(etags)) => etags.iter().any(|etag| etag.weak_eq(&self.etag)),
+            None => false,
+        }
+    }
+
+    /// Set `ETag` and `Cache-Control` headers of response
+    pub fn set_headers(&self, response: &mut HttpResponseBuilder) {
+        response
+            .set_header(ETAG, self.etag.to_string())
+            .set(CacheControl(vec![
+                CacheDirective::Public,
+                CacheDirective::MaxAge(self.max_age as u32),
+            ]));
+    }
+}
+
+#[cfg(test)]
+#[path = "./http_cache_test.rs"]
+mod http_cache_test;
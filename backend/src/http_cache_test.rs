@@ -0,0 +1,54 @@
+use super::*;
+use actix_web::{http::header::IF_NONE_MATCH, test::TestRequest, HttpResponse};
+
+#[test]
+fn test_etag_changes_with_generation() {
+    let http_cache = HttpCache::new(1, 3600).unwrap();
+    assert!(http_cache.etag == HttpCache::new(1, 3600).unwrap().etag);
+    assert!(http_cache.etag != HttpCache::new(2, 3600).unwrap().etag);
+    assert!(HttpCache::new(1, 0).is_none());
+}
+
+#[test]
+fn test_is_fresh() {
+    let http_cache = HttpCache::new(1, 3600).unwrap();
+    let etag = http_cache.etag.to_string();
+    for (if_none_match, fresh) in [
+        (None, false),
+        (Some(etag.as_str()), true),
+        (Some("\"other\", W/\"other\""), false),
+        (Some("*"), true),
+    ] {
+        let mut request = TestRequest::default();
+        if let Some(if_none_match) = if_none_match {
+            request = request.header(IF_NONE_MATCH, if_none_match);
+        }
+        assert_eq!(
+            http_cache.is_fresh(&request.to_http_request()),
+            fresh,
+            "If-None-Match: {:?}",
+            if_none_match
+        );
+    }
+}
+
+#[test]
+fn test_set_headers() {
+    let http_cache = HttpCache::new(1, 60).unwrap();
+    let mut response = HttpResponse::Ok();
+    http_cache.set_headers(&mut response);
+    let response = response.finish();
+    assert_eq!(
+        response.headers().get(ETAG).unwrap().to_str().unwrap(),
+        http_cache.etag.to_string()
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get("Cache-Control")
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        "public, max-age=60"
+    );
+}
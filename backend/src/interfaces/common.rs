@@ -4,7 +4,7 @@ use actix_web::{
     http::{header, StatusCode},
     HttpResponse,
 };
-use chrono::{serde::ts_milliseconds::serialize as to_milli_ts, DateTime, Utc};
+use chrono::{serde::ts_milliseconds::serialize as to_milli_ts, DateTime, Duration, Utc};
 use derive_more::Display;
 use search_base::ProjectDocument;
 use serde::{Deserialize, Serialize};
@@ -12,6 +12,10 @@ use serde_with::rust::display_fromstr::deserialize as deserialize_fromstr;
 use std::convert::{From, TryFrom};
 use std::ops::Range;
 use tantivy::{error::TantivyError, schema::Field, DocAddress};
+use utoipa::{
+    openapi::{ObjectBuilder, RefOr, Schema, SchemaType},
+    ToSchema,
+};
 
 /// Max length of title and/or title snippet (in UTF-8 chars)
 pub const MAX_TITLE_LENGTH: usize = 32;
@@ -26,16 +30,27 @@ pub const MAX_CODE_LENGTH: usize = 256;
 pub const MAX_KEY_HINTS_COUNT: usize = 8;
 
 /// Snippet
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Snippet {
     pub fragments: String,
     /// Position of highlighted segments in fragments
     ///
     /// This field may be empty if no snippet is matched
+    #[schema(value_type = Vec<HighlightedRange>)]
     pub highlighted_positions: Vec<Range<usize>>,
 }
 
+/// Schema of `Range<usize>` in [`Snippet`], only used in OpenAPI document
+#[allow(dead_code)]
+#[derive(ToSchema)]
+pub struct HighlightedRange {
+    /// Inclusive, indexed in byte
+    start: usize,
+    /// Exclusive, indexed in byte
+    end: usize,
+}
+
 impl Snippet {
     /// Create a new snippet from fragments and highlighted positions
     ///
@@ -70,7 +85,7 @@ impl Snippet {
 /// The same structure as [`tantivy::DocAddress`].
 ///
 /// Tantivy's `DocAddress` does not implement `Serialize` trait.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct UdiabDocAddress {
     // see https://docs.rs/serde_qs/0.8.5/serde_qs/index.html#flatten-workaround
@@ -107,7 +122,7 @@ impl From<UdiabDocAddress> for DocAddress {
 }
 
 /// Article structure used for searching
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchedArticleInfo {
     /// URL of article
@@ -132,12 +147,39 @@ pub struct SearchedArticleInfo {
     ///
     /// In format of milliseconds in UTC
     #[serde(serialize_with = "to_milli_ts")]
+    #[schema(value_type = i64)]
     pub time: DateTime<Utc>,
     /// Doc address
     pub address: UdiabDocAddress,
 }
 
-#[derive(Deserialize)]
+/// A page of searched articles
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrievedInfo {
+    pub article_infos: Vec<SearchedArticleInfo>,
+    /// Opaque cursor to fetch the next page.
+    ///
+    /// Only presented if this page is full.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// Whether some matches may be missing, since regex expansion hit its
+    /// limit of terms or time, or deadline of search expired
+    pub partial: bool,
+}
+
+/// Schema of enums encoded as numeric string discriminants, e.g., `"0"`
+fn discriminant_schema(description: &str, variant_count: usize) -> RefOr<Schema> {
+    ObjectBuilder::new()
+        .schema_type(SchemaType::String)
+        .enum_values(Some(
+            (0..variant_count).map(|discriminant| discriminant.to_string()),
+        ))
+        .description(Some(description))
+        .into()
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AdvanceSearchOptions {
     pub sort_by: SearchSortBy,
@@ -146,14 +188,23 @@ pub struct AdvanceSearchOptions {
 }
 
 /// The search result is sorted by ...
-#[derive(Deserialize)]
-#[serde(try_from = "String")]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
 pub enum SearchSortBy {
     Time,
     Hot,
     Relevance,
 }
 
+impl<'s> ToSchema<'s> for SearchSortBy {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "SearchSortBy",
+            discriminant_schema("0: time, 1: hot, 2: relevance", 3),
+        )
+    }
+}
+
 impl TryFrom<String> for SearchSortBy {
     type Error = String;
 
@@ -170,9 +221,20 @@ impl TryFrom<String> for SearchSortBy {
     }
 }
 
+impl From<SearchSortBy> for String {
+    fn from(sort_by: SearchSortBy) -> Self {
+        match sort_by {
+            SearchSortBy::Time => "0",
+            SearchSortBy::Hot => "1",
+            SearchSortBy::Relevance => "2",
+        }
+        .to_string()
+    }
+}
+
 /// Search field
-#[derive(Deserialize)]
-#[serde(try_from = "String")]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
 pub enum SearchField {
     /// Only search title
     Title,
@@ -184,6 +246,15 @@ pub enum SearchField {
     All,
 }
 
+impl<'s> ToSchema<'s> for SearchField {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "SearchField",
+            discriminant_schema("0: title, 1: code, 2: all", 3),
+        )
+    }
+}
+
 impl TryFrom<String> for SearchField {
     type Error = String;
 
@@ -200,6 +271,17 @@ impl TryFrom<String> for SearchField {
     }
 }
 
+impl From<SearchField> for String {
+    fn from(search_field: SearchField) -> Self {
+        match search_field {
+            SearchField::Title => "0",
+            SearchField::Code => "1",
+            SearchField::All => "2",
+        }
+        .to_string()
+    }
+}
+
 impl SearchField {
     /// Get corresponding tantivy::schema::Field
     pub fn tantivy_fields(&self, project_document: ProjectDocument) -> Vec<Field> {
@@ -216,8 +298,8 @@ impl SearchField {
 }
 
 /// Search Method
-#[derive(Deserialize)]
-#[serde(try_from = "String")]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "String", into = "String")]
 pub enum SearchMethod {
     /// Naive search method
     Naive,
@@ -227,6 +309,23 @@ pub enum SearchMethod {
     Regex,
 }
 
+impl<'s> ToSchema<'s> for SearchMethod {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "SearchMethod",
+            discriminant_schema("0: naive, 1: complex, 2: regex", 3),
+        )
+    }
+}
+
+impl SearchMethod {
+    /// Whether searching with this method is expensive, i.e., subject to the
+    /// cap of concurrent expensive queries
+    pub fn is_expensive(self) -> bool {
+        matches!(self, SearchMethod::Complex | SearchMethod::Regex)
+    }
+}
+
 impl TryFrom<String> for SearchMethod {
     type Error = String;
 
@@ -243,10 +342,341 @@ impl TryFrom<String> for SearchMethod {
     }
 }
 
-pub const TOP_ARTICLE_INFOS_COUNT: usize = 10;
+impl From<SearchMethod> for String {
+    fn from(search_method: SearchMethod) -> Self {
+        match search_method {
+            SearchMethod::Naive => "0",
+            SearchMethod::Complex => "1",
+            SearchMethod::Regex => "2",
+        }
+        .to_string()
+    }
+}
+
+/// Key of a sort list entry
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SortField {
+    Relevance,
+    Time,
+    /// Scraped likes blended with clicks
+    Hot,
+    /// Scraped likes only
+    Likes,
+}
+
+/// Order of a sort list entry
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// One entry of sort list. Later entries break ties of former ones.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SortSpec {
+    pub by: SortField,
+    /// Defaults to [`SortOrder::Desc`]
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+impl From<SearchSortBy> for SortSpec {
+    fn from(sort_by: SearchSortBy) -> Self {
+        let by = match sort_by {
+            SearchSortBy::Time => SortField::Time,
+            SearchSortBy::Hot => SortField::Hot,
+            SearchSortBy::Relevance => SortField::Relevance,
+        };
+        Self {
+            by,
+            order: SortOrder::Desc,
+        }
+    }
+}
+
+/// Default count of top article infos (in each group)
+pub const DEFAULT_TOP_ARTICLE_INFOS_COUNT: usize = 10;
+
+/// Max count of top article infos (in each group)
+pub const MAX_TOP_ARTICLE_INFOS_COUNT: usize = 100;
+
+/// Time window of top articles, ending at now
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(try_from = "String")]
+pub enum TopInfoWindow {
+    /// Last 24 hours
+    Day,
+    /// Last 7 days
+    Week,
+    /// Last 30 days
+    #[default]
+    Month,
+    /// Custom range given by `start` and `end`
+    Custom,
+}
+
+impl<'s> ToSchema<'s> for TopInfoWindow {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "TopInfoWindow",
+            discriminant_schema("0: day, 1: week, 2: month, 3: custom", 4),
+        )
+    }
+}
+
+impl TryFrom<String> for TopInfoWindow {
+    type Error = String;
+
+    fn try_from(discriminant: String) -> Result<Self, Self::Error> {
+        match discriminant.as_str() {
+            "0" => Ok(TopInfoWindow::Day),
+            "1" => Ok(TopInfoWindow::Week),
+            "2" => Ok(TopInfoWindow::Month),
+            "3" => Ok(TopInfoWindow::Custom),
+            _ => Err(format!(
+                "Unknown discriminant for TopInfoWindow: {}.",
+                discriminant
+            )),
+        }
+    }
+}
+
+impl TopInfoWindow {
+    /// Length of window, `None` for custom window
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            TopInfoWindow::Day => Some(Duration::days(1)),
+            TopInfoWindow::Week => Some(Duration::weeks(1)),
+            TopInfoWindow::Month => Some(Duration::days(30)),
+            TopInfoWindow::Custom => None,
+        }
+    }
+}
+
+/// Default count of top sources in aggregations
+pub const DEFAULT_TOP_SOURCES_COUNT: usize = 10;
+
+/// Max count of top sources in aggregations
+pub const MAX_TOP_SOURCES_COUNT: usize = 100;
+
+/// Default lower bounds of likes buckets, see
+/// [`crate::interfaces::AggregationsRequest::likes_boundaries`]
+pub const DEFAULT_LIKES_BOUNDARIES: &[u64] = &[10, 100, 1000];
+
+/// Max count of likes boundaries
+pub const MAX_LIKES_BOUNDARIES_COUNT: usize = 32;
+
+/// Interval of date histogram buckets, in UTC
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+#[serde(try_from = "String")]
+pub enum HistogramInterval {
+    Day,
+    /// Weeks starting on Monday
+    Week,
+    #[default]
+    Month,
+    Year,
+}
+
+impl<'s> ToSchema<'s> for HistogramInterval {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "HistogramInterval",
+            discriminant_schema("0: day, 1: week, 2: month, 3: year", 4),
+        )
+    }
+}
+
+impl TryFrom<String> for HistogramInterval {
+    type Error = String;
+
+    fn try_from(discriminant: String) -> Result<Self, Self::Error> {
+        match discriminant.as_str() {
+            "0" => Ok(HistogramInterval::Day),
+            "1" => Ok(HistogramInterval::Week),
+            "2" => Ok(HistogramInterval::Month),
+            "3" => Ok(HistogramInterval::Year),
+            _ => Err(format!(
+                "Unknown discriminant for HistogramInterval: {}.",
+                discriminant
+            )),
+        }
+    }
+}
+
+/// Count of matched articles in a bucket of date histogram
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DateBucket {
+    /// Start of bucket, in milliseconds in UTC
+    #[serde(serialize_with = "to_milli_ts")]
+    #[schema(value_type = i64)]
+    pub start: DateTime<Utc>,
+    pub count: u64,
+}
+
+/// Count of matched articles whose likes are in `[from, to)`
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LikesBucket {
+    pub from: u64,
+    /// Absent for the last bucket, which is unbounded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<u64>,
+    pub count: u64,
+}
+
+/// Count of matched articles from a source site
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceCount {
+    /// Source site, e.g., `csdn.net`
+    pub source: String,
+    pub count: u64,
+}
+
+/// Aggregations of matched articles
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Aggregations {
+    /// Count of matched articles
+    pub total: usize,
+    /// Buckets in ascending order of time. Buckets without matches are
+    /// omitted
+    pub date_histogram: Vec<DateBucket>,
+    /// Buckets in ascending order of likes, including empty ones
+    pub likes_buckets: Vec<LikesBucket>,
+    /// Sources with most matches, in descending order of count
+    pub top_sources: Vec<SourceCount>,
+    /// Whether some matches may be missing, since regex expansion hit its
+    /// limit of terms or time, or deadline of search expired
+    pub partial: bool,
+}
+
+/// A token produced by a tokenizer
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyzedToken {
+    pub text: String,
+    /// Start of token in text, indexed in byte (inclusive)
+    pub offset_from: usize,
+    /// End of token in text, indexed in byte (exclusive)
+    pub offset_to: usize,
+    /// Position of token, used by phrase queries
+    pub position: usize,
+}
+
+/// Tokens of a text produced by a tokenizer
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Analysis {
+    /// Name of tokenizer, e.g., `CANG_JIE`
+    pub tokenizer: String,
+    pub tokens: Vec<AnalyzedToken>,
+}
+
+/// Node of explanation tree of relevance score, see
+/// [`tantivy::query::Explanation`]
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplanationNode {
+    pub value: f32,
+    /// What the value is, e.g., `BM25`, `idf`, or `Boost`
+    pub description: String,
+    /// Values combined into this one
+    #[serde(default)]
+    pub details: Vec<ExplanationNode>,
+    #[serde(default)]
+    pub context: Vec<String>,
+}
+
+/// Tokens of key in a searched field
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldTokens {
+    /// Name of field, e.g., `title`
+    pub field: String,
+    /// Name of tokenizer of field, e.g., `mixed`
+    pub tokenizer: String,
+    pub tokens: Vec<ExplainedToken>,
+}
+
+/// Token of key, and whether it is a term of the article
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainedToken {
+    #[serde(flatten)]
+    pub token: AnalyzedToken,
+    /// Whether the field of the article contains this term
+    pub matched: bool,
+}
+
+/// Why an article is scored as it is for a query
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Explain {
+    /// Whether the article matches the query
+    pub matched: bool,
+    /// Explanation of relevance score, absent if not matched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<ExplanationNode>,
+    /// Tokens of key in each searched field
+    pub field_tokens: Vec<FieldTokens>,
+    /// Whether regex expansion hit its limit of terms or time, so that the
+    /// query may miss terms
+    pub partial: bool,
+}
+
+/// Top articles are grouped by ...
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(try_from = "String")]
+pub enum TopInfoGroupBy {
+    /// Source site, e.g., csdn.net
+    Source,
+    /// Programming language of code in article
+    CodeLanguage,
+}
+
+impl<'s> ToSchema<'s> for TopInfoGroupBy {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "TopInfoGroupBy",
+            discriminant_schema("0: source, 1: code language", 2),
+        )
+    }
+}
+
+impl TryFrom<String> for TopInfoGroupBy {
+    type Error = String;
+
+    fn try_from(discriminant: String) -> Result<Self, Self::Error> {
+        match discriminant.as_str() {
+            "0" => Ok(TopInfoGroupBy::Source),
+            "1" => Ok(TopInfoGroupBy::CodeLanguage),
+            _ => Err(format!(
+                "Unknown discriminant for TopInfoGroupBy: {}.",
+                discriminant
+            )),
+        }
+    }
+}
+
+impl TopInfoGroupBy {
+    /// Get corresponding facet field
+    pub fn tantivy_field(&self, project_document: ProjectDocument) -> Field {
+        match self {
+            TopInfoGroupBy::Source => project_document.source,
+            TopInfoGroupBy::CodeLanguage => project_document.language,
+        }
+    }
+}
 
 /// Article structure used for today's top
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Clone, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TopArticleInfo {
     /// URL of article
@@ -257,8 +687,31 @@ pub struct TopArticleInfo {
     pub likes: u64,
 }
 
+/// Top articles of one group
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TopArticleGroup {
+    /// Name of group, e.g., `csdn.net` or `rust`
+    pub name: String,
+    /// Count of all articles of this group in time window
+    pub count: u64,
+    pub top_article_infos: Vec<TopArticleInfo>,
+}
+
+/// Top articles in a time window
+#[derive(Serialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TopInfo {
+    pub top_article_infos: Vec<TopArticleInfo>,
+    /// Top articles of each group, sorted by count of group.
+    ///
+    /// Only presented if grouping is requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<TopArticleGroup>>,
+}
+
 /// Article structure used for more like this query
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Debug, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MoreLikeThisArticleInfo {
     /// URL of article
@@ -273,12 +726,44 @@ pub struct MoreLikeThisArticleInfo {
     ///
     /// In format of milliseconds in UTC
     #[serde(serialize_with = "to_milli_ts")]
+    #[schema(value_type = i64)]
     pub time: DateTime<Utc>,
 }
 
+/// Statistics of a query cache
+#[derive(Serialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Hits divided by total lookups, 0 if never looked up
+    pub hit_ratio: f64,
+    pub len: usize,
+    pub capacity: usize,
+}
+
 /// Errors which will be sent to user
 #[derive(Debug, Display)]
 pub enum UserError {
+    #[display(fmt = "Bad request: {}", _0)]
+    BadRequest(String),
+    #[display(fmt = "Unauthorized: {}", _0)]
+    Unauthorized(String),
+    #[display(fmt = "Forbidden: {}", _0)]
+    Forbidden(String),
+    #[display(fmt = "Not found: {}", _0)]
+    NotFound(String),
+    #[display(fmt = "Conflict: {}", _0)]
+    Conflict(String),
+    #[display(fmt = "Service unavailable: {}", _0)]
+    Unavailable(String),
+    #[display(fmt = "Timeout: {}", _0)]
+    Timeout(String),
+    /// Client should retry after `retry_after` seconds
+    #[display(fmt = "Too many requests, retry after {} seconds", retry_after)]
+    TooManyRequests {
+        retry_after: u64,
+    },
     UnexpectedTantivy {
         tantivy_error: TantivyError,
     },
@@ -290,11 +775,24 @@ impl std::error::Error for UserError {}
 
 impl error::ResponseError for UserError {
     fn error_response(&self) -> HttpResponse {
-        HttpResponseBuilder::new(self.status_code())
-            .set_header(header::CONTENT_TYPE, "text/html; charset=utf-8")
-            .body(self.to_string())
+        let mut response = HttpResponseBuilder::new(self.status_code());
+        response.set_header(header::CONTENT_TYPE, "text/html; charset=utf-8");
+        if let UserError::TooManyRequests { retry_after } = self {
+            response.set_header(header::RETRY_AFTER, retry_after.to_string());
+        }
+        response.body(self.to_string())
     }
     fn status_code(&self) -> StatusCode {
-        StatusCode::INTERNAL_SERVER_ERROR
+        match self {
+            UserError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            UserError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            UserError::Forbidden(_) => StatusCode::FORBIDDEN,
+            UserError::NotFound(_) => StatusCode::NOT_FOUND,
+            UserError::Conflict(_) => StatusCode::CONFLICT,
+            UserError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            UserError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            UserError::TooManyRequests { .. } => StatusCode::TOO_MANY_REQUESTS,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
     }
 }
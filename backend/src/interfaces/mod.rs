@@ -1,6 +1,7 @@
 mod common;
 mod request;
 mod response;
+pub mod v2;
 
 pub use common::*;
 pub use request::*;
@@ -1,28 +1,377 @@
-use serde::Deserialize;
+use search_base::ProjectDocument;
+use serde::{Deserialize, Serialize};
+use tantivy::schema::Field;
+use utoipa::{IntoParams, ToSchema};
 
 use super::common::*;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct KeyHintsRequest {
     pub key: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct RetrievedInfoRequest {
     pub key: String,
     #[serde(flatten)]
+    #[param(style = Form, explode, inline)]
     pub advanced_search_options: AdvanceSearchOptions,
+    /// Ignored if `cursor` is presented
+    #[serde(default)]
     pub offset: usize,
+    /// `nextCursor` returned by previous page
+    pub cursor: Option<String>,
     pub page_size: usize,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Clone, PartialEq, Eq, Hash, IntoParams)]
 #[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct TopInfoRequest {
+    /// Defaults to [`TopInfoWindow::Month`]
+    #[serde(default)]
+    pub window: TopInfoWindow,
+    /// Start of custom window, in milliseconds in UTC (inclusive)
+    pub start: Option<i64>,
+    /// End of custom window, in milliseconds in UTC (exclusive)
+    pub end: Option<i64>,
+    /// Defaults to [`DEFAULT_TOP_ARTICLE_INFOS_COUNT`], and will not exceed
+    /// [`MAX_TOP_ARTICLE_INFOS_COUNT`]
+    pub limit: Option<usize>,
+    pub group_by: Option<TopInfoGroupBy>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AggregationsRequest {
+    pub key: String,
+    pub search_field: SearchField,
+    pub search_method: SearchMethod,
+    /// Defaults to [`HistogramInterval::Month`]
+    #[serde(default)]
+    pub interval: HistogramInterval,
+    /// Comma separated lower bounds of likes buckets in ascending order,
+    /// e.g., `10,100` for buckets `[0, 10)`, `[10, 100)` and `[100, ∞)`.
+    /// Defaults to [`DEFAULT_LIKES_BOUNDARIES`]
+    pub likes_boundaries: Option<String>,
+    /// Count of top sources. Defaults to [`DEFAULT_TOP_SOURCES_COUNT`], and
+    /// will not exceed [`MAX_TOP_SOURCES_COUNT`]
+    pub top_sources: Option<usize>,
+}
+
+impl AggregationsRequest {
+    /// Parsed likes boundaries, see [`AggregationsRequest::likes_boundaries`]
+    pub fn likes_boundaries(&self) -> Result<Vec<u64>, String> {
+        let likes_boundaries = match &self.likes_boundaries {
+            Some(likes_boundaries) if !likes_boundaries.is_empty() => likes_boundaries
+                .split(',')
+                .map(|boundary| {
+                    boundary
+                        .trim()
+                        .parse::<u64>()
+                        .map_err(|_| format!("Invalid likes boundary: {:?}", boundary))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => vec![],
+            None => DEFAULT_LIKES_BOUNDARIES.to_vec(),
+        };
+        if likes_boundaries.len() > MAX_LIKES_BOUNDARIES_COUNT {
+            return Err(format!(
+                "likesBoundaries must not contain more than {} boundaries",
+                MAX_LIKES_BOUNDARIES_COUNT
+            ));
+        }
+        if likes_boundaries
+            .windows(2)
+            .any(|boundaries| boundaries[0] >= boundaries[1])
+        {
+            return Err("likesBoundaries must be strictly ascending".to_string());
+        }
+        Ok(likes_boundaries)
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ExplainRequest {
+    pub key: String,
+    pub search_field: SearchField,
+    pub search_method: SearchMethod,
+    /// URL of explained article
+    pub url: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AnalyzeRequest {
+    /// Text to run through every tokenizer
+    pub text: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
 pub struct MoreLikeThisRequest {
     #[serde(flatten)]
+    #[param(style = Form, explode, inline)]
     pub address: UdiabDocAddress,
     pub offset: usize,
     pub page_size: usize,
 }
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ClickRequest {
+    /// Impression id returned by retrieved info
+    pub impression_id: String,
+    pub key: String,
+    /// Position of clicked article in the whole result list (0-based)
+    pub position: usize,
+    /// URL of clicked article, which is the stable identity of article
+    pub url: String,
+}
+
+/// Body of `POST /search`
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchRequest {
+    /// Matches all articles if absent
+    pub query: Option<QueryClause>,
+    /// Restrictions on matched articles, which do not affect relevance
+    #[serde(default)]
+    pub filters: Vec<SearchFilter>,
+    /// Defaults to sorting by relevance
+    #[serde(default)]
+    pub sort: Vec<SortSpec>,
+    #[serde(default)]
+    pub pagination: Pagination,
+}
+
+/// A clause of query DSL
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum QueryClause {
+    /// Match key in fields with a search method
+    Match(MatchClause),
+    /// Combine clauses
+    Bool(BoolClause),
+    /// Match all articles
+    MatchAll {},
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchClause {
+    pub key: String,
+    /// Defaults to [`MatchField::All`]
+    #[serde(default)]
+    pub field: MatchField,
+    /// Defaults to [`MatchMethod::Naive`]
+    #[serde(default)]
+    pub method: MatchMethod,
+}
+
+/// Boolean combination of clauses
+///
+/// An article matches if it matches all `must` clauses and none of `mustNot`
+/// clauses. `should` clauses are required only if there is no `must`
+/// clause, and add to relevance otherwise.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BoolClause {
+    #[serde(default)]
+    pub must: Vec<QueryClause>,
+    #[serde(default)]
+    pub should: Vec<QueryClause>,
+    #[serde(default)]
+    pub must_not: Vec<QueryClause>,
+}
+
+impl QueryClause {
+    /// Whether any match clause uses an expensive search method, see
+    /// [`SearchMethod::is_expensive`]
+    pub fn is_expensive(&self) -> bool {
+        match self {
+            QueryClause::Match(match_clause) => {
+                SearchMethod::from(match_clause.method).is_expensive()
+            }
+            QueryClause::Bool(bool_clause) => bool_clause
+                .must
+                .iter()
+                .chain(&bool_clause.should)
+                .chain(&bool_clause.must_not)
+                .any(QueryClause::is_expensive),
+            QueryClause::MatchAll {} => false,
+        }
+    }
+}
+
+/// Field searched by a match clause
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchField {
+    Title,
+    Body,
+    Code,
+    /// Title, body and code
+    #[default]
+    All,
+}
+
+impl MatchField {
+    /// Get corresponding tantivy::schema::Field
+    pub fn tantivy_fields(&self, project_document: ProjectDocument) -> Vec<Field> {
+        let ProjectDocument {
+            title, body, code, ..
+        } = project_document;
+
+        match &self {
+            MatchField::Title => vec![title],
+            MatchField::Body => vec![body],
+            MatchField::Code => vec![code],
+            MatchField::All => vec![title, body, code],
+        }
+    }
+}
+
+/// Search method of a match clause, see [`SearchMethod`]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchMethod {
+    #[default]
+    Naive,
+    Complex,
+    Regex,
+}
+
+impl From<MatchMethod> for SearchMethod {
+    fn from(match_method: MatchMethod) -> Self {
+        match match_method {
+            MatchMethod::Naive => SearchMethod::Naive,
+            MatchMethod::Complex => SearchMethod::Complex,
+            MatchMethod::Regex => SearchMethod::Regex,
+        }
+    }
+}
+
+/// Filter of search request
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchFilter {
+    /// Time range, in milliseconds in UTC
+    #[serde(rename_all = "camelCase")]
+    Time {
+        /// Inclusive
+        gte: Option<i64>,
+        /// Exclusive
+        lt: Option<i64>,
+    },
+    /// Source site, e.g., `csdn.net`
+    Source(String),
+    /// Programming language of code, e.g., `rust`
+    Language(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Pagination {
+    /// Ignored if `cursor` is presented
+    #[serde(default)]
+    pub offset: usize,
+    /// `nextCursor` returned by previous page
+    pub cursor: Option<String>,
+    #[serde(default = "default_page_size")]
+    pub page_size: usize,
+}
+
+fn default_page_size() -> usize {
+    10
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            cursor: None,
+            page_size: default_page_size(),
+        }
+    }
+}
+
+/// Query of `GET /export`
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ExportRequest {
+    /// Exports all articles if absent
+    pub key: Option<String>,
+    /// Defaults to [`MatchField::All`]
+    #[serde(default)]
+    pub field: MatchField,
+    /// Defaults to [`MatchMethod::Naive`]
+    #[serde(default)]
+    pub method: MatchMethod,
+    /// Source site, e.g., `csdn.net`
+    pub source: Option<String>,
+    /// Programming language of code, e.g., `rust`
+    pub language: Option<String>,
+    /// Start of time range, in milliseconds in UTC (inclusive)
+    pub start: Option<i64>,
+    /// End of time range, in milliseconds in UTC (exclusive)
+    pub end: Option<i64>,
+    /// Defaults to [`ExportFormat::Csv`]
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Whether to export the whole body of articles
+    #[serde(default)]
+    pub include_body: bool,
+}
+
+impl ExportRequest {
+    /// Match clause of key, if any
+    pub fn query(&self) -> Option<QueryClause> {
+        self.key.as_ref().map(|key| {
+            QueryClause::Match(MatchClause {
+                key: key.clone(),
+                field: self.field,
+                method: self.method,
+            })
+        })
+    }
+
+    /// Filters of source, language and time range
+    pub fn filters(&self) -> Vec<SearchFilter> {
+        let mut filters = vec![];
+        if let Some(source) = &self.source {
+            filters.push(SearchFilter::Source(source.clone()));
+        }
+        if let Some(language) = &self.language {
+            filters.push(SearchFilter::Language(language.clone()));
+        }
+        if self.start.is_some() || self.end.is_some() {
+            filters.push(SearchFilter::Time {
+                gte: self.start,
+                lt: self.end,
+            });
+        }
+        filters
+    }
+}
+
+/// Format of exported articles
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// Comma separated values with a header row
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Jsonl,
+}
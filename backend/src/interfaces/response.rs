@@ -1,8 +1,10 @@
+use chrono::{serde::ts_milliseconds::serialize as to_milli_ts, DateTime, Utc};
 use serde::Serialize;
+use utoipa::ToSchema;
 
 use super::common::*;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct KeyHintsResponse {
     pub key_hints: Vec<Snippet>,
@@ -10,26 +12,83 @@ pub struct KeyHintsResponse {
     pub duration: u128,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RetrievedInfoResponse {
-    pub article_infos: Vec<SearchedArticleInfo>,
+    #[serde(flatten)]
+    pub retrieved_info: RetrievedInfo,
+    /// Identity of this page of results, should be sent back when clicking
+    pub impression_id: String,
     /// In milli-seconds
     pub duration: u128,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct TopArticleInfoResponse {
-    pub top_article_infos: Vec<TopArticleInfo>,
+    #[serde(flatten)]
+    pub top_info: TopInfo,
     /// In milli-seconds
     pub duration: u128,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AggregationsResponse {
+    #[serde(flatten)]
+    pub aggregations: Aggregations,
+    /// In milli-seconds
+    pub duration: u128,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalyzeResponse {
+    /// One per tokenizer registered by [`search_base::index`]
+    pub analyses: Vec<Analysis>,
+    /// In milli-seconds
+    pub duration: u128,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplainResponse {
+    #[serde(flatten)]
+    pub explain: Explain,
+    /// In milli-seconds
+    pub duration: u128,
+}
+
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct MoreLikeThisResponse {
     pub more_like_this_article_infos: Vec<MoreLikeThisArticleInfo>,
     /// In milli-seconds
     pub duration: u128,
 }
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStatsResponse {
+    pub key_hints: CacheStats,
+    pub retrieved_info: CacheStats,
+    pub top_info: CacheStats,
+}
+
+/// A line of `GET /export`
+#[derive(Serialize, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportedArticle {
+    pub url: String,
+    pub title: String,
+    /// In format of milliseconds in UTC
+    #[serde(serialize_with = "to_milli_ts")]
+    #[schema(value_type = i64)]
+    pub time: DateTime<Utc>,
+    pub likes: u64,
+    /// Source site, e.g., `csdn.net`
+    pub source: String,
+    /// Whole body, only presented if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+}
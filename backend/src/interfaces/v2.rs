@@ -0,0 +1,283 @@
+//! Requests of `/api/v2`, where enum values are named instead of numeric
+//! discriminants, e.g., `sortBy=hot` instead of `sortBy=1`.
+//!
+//! Each request is converted into its v1 counterpart, so that both versions
+//! are served by the same model.
+
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use super::common;
+use super::request;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = v2::AdvanceSearchOptions)]
+pub struct AdvanceSearchOptions {
+    pub sort_by: SearchSortBy,
+    pub search_field: SearchField,
+    pub search_method: SearchMethod,
+}
+
+impl From<AdvanceSearchOptions> for common::AdvanceSearchOptions {
+    fn from(advanced_search_options: AdvanceSearchOptions) -> Self {
+        Self {
+            sort_by: advanced_search_options.sort_by.into(),
+            search_field: advanced_search_options.search_field.into(),
+            search_method: advanced_search_options.search_method.into(),
+        }
+    }
+}
+
+/// See [`common::SearchSortBy`]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = v2::SearchSortBy)]
+pub enum SearchSortBy {
+    Time,
+    Hot,
+    Relevance,
+}
+
+impl From<SearchSortBy> for common::SearchSortBy {
+    fn from(sort_by: SearchSortBy) -> Self {
+        match sort_by {
+            SearchSortBy::Time => common::SearchSortBy::Time,
+            SearchSortBy::Hot => common::SearchSortBy::Hot,
+            SearchSortBy::Relevance => common::SearchSortBy::Relevance,
+        }
+    }
+}
+
+/// See [`common::SearchField`]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = v2::SearchField)]
+pub enum SearchField {
+    Title,
+    Code,
+    All,
+}
+
+impl From<SearchField> for common::SearchField {
+    fn from(search_field: SearchField) -> Self {
+        match search_field {
+            SearchField::Title => common::SearchField::Title,
+            SearchField::Code => common::SearchField::Code,
+            SearchField::All => common::SearchField::All,
+        }
+    }
+}
+
+/// See [`common::SearchMethod`]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = v2::SearchMethod)]
+pub enum SearchMethod {
+    Naive,
+    Complex,
+    Regex,
+}
+
+impl From<SearchMethod> for common::SearchMethod {
+    fn from(search_method: SearchMethod) -> Self {
+        match search_method {
+            SearchMethod::Naive => common::SearchMethod::Naive,
+            SearchMethod::Complex => common::SearchMethod::Complex,
+            SearchMethod::Regex => common::SearchMethod::Regex,
+        }
+    }
+}
+
+/// See [`common::TopInfoWindow`]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = v2::TopInfoWindow)]
+pub enum TopInfoWindow {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Custom,
+}
+
+impl From<TopInfoWindow> for common::TopInfoWindow {
+    fn from(window: TopInfoWindow) -> Self {
+        match window {
+            TopInfoWindow::Day => common::TopInfoWindow::Day,
+            TopInfoWindow::Week => common::TopInfoWindow::Week,
+            TopInfoWindow::Month => common::TopInfoWindow::Month,
+            TopInfoWindow::Custom => common::TopInfoWindow::Custom,
+        }
+    }
+}
+
+/// See [`common::TopInfoGroupBy`]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = v2::TopInfoGroupBy)]
+pub enum TopInfoGroupBy {
+    Source,
+    CodeLanguage,
+}
+
+impl From<TopInfoGroupBy> for common::TopInfoGroupBy {
+    fn from(group_by: TopInfoGroupBy) -> Self {
+        match group_by {
+            TopInfoGroupBy::Source => common::TopInfoGroupBy::Source,
+            TopInfoGroupBy::CodeLanguage => common::TopInfoGroupBy::CodeLanguage,
+        }
+    }
+}
+
+/// See [`request::RetrievedInfoRequest`]
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct RetrievedInfoRequest {
+    pub key: String,
+    #[serde(flatten)]
+    #[param(style = Form, explode, inline)]
+    pub advanced_search_options: AdvanceSearchOptions,
+    #[serde(default)]
+    pub offset: usize,
+    pub cursor: Option<String>,
+    pub page_size: usize,
+}
+
+impl From<RetrievedInfoRequest> for request::RetrievedInfoRequest {
+    fn from(retrieved_info_request: RetrievedInfoRequest) -> Self {
+        let RetrievedInfoRequest {
+            key,
+            advanced_search_options,
+            offset,
+            cursor,
+            page_size,
+        } = retrieved_info_request;
+        Self {
+            key,
+            advanced_search_options: advanced_search_options.into(),
+            offset,
+            cursor,
+            page_size,
+        }
+    }
+}
+
+/// See [`common::HistogramInterval`]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug, Default, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[schema(as = v2::HistogramInterval)]
+pub enum HistogramInterval {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Year,
+}
+
+impl From<HistogramInterval> for common::HistogramInterval {
+    fn from(interval: HistogramInterval) -> Self {
+        match interval {
+            HistogramInterval::Day => common::HistogramInterval::Day,
+            HistogramInterval::Week => common::HistogramInterval::Week,
+            HistogramInterval::Month => common::HistogramInterval::Month,
+            HistogramInterval::Year => common::HistogramInterval::Year,
+        }
+    }
+}
+
+/// See [`request::AggregationsRequest`]
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AggregationsRequest {
+    pub key: String,
+    pub search_field: SearchField,
+    pub search_method: SearchMethod,
+    #[serde(default)]
+    pub interval: HistogramInterval,
+    pub likes_boundaries: Option<String>,
+    pub top_sources: Option<usize>,
+}
+
+impl From<AggregationsRequest> for request::AggregationsRequest {
+    fn from(aggregations_request: AggregationsRequest) -> Self {
+        let AggregationsRequest {
+            key,
+            search_field,
+            search_method,
+            interval,
+            likes_boundaries,
+            top_sources,
+        } = aggregations_request;
+        Self {
+            key,
+            search_field: search_field.into(),
+            search_method: search_method.into(),
+            interval: interval.into(),
+            likes_boundaries,
+            top_sources,
+        }
+    }
+}
+
+/// See [`request::ExplainRequest`]
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ExplainRequest {
+    pub key: String,
+    pub search_field: SearchField,
+    pub search_method: SearchMethod,
+    pub url: String,
+}
+
+impl From<ExplainRequest> for request::ExplainRequest {
+    fn from(explain_request: ExplainRequest) -> Self {
+        let ExplainRequest {
+            key,
+            search_field,
+            search_method,
+            url,
+        } = explain_request;
+        Self {
+            key,
+            search_field: search_field.into(),
+            search_method: search_method.into(),
+            url,
+        }
+    }
+}
+
+/// See [`request::TopInfoRequest`]
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct TopInfoRequest {
+    #[serde(default)]
+    pub window: TopInfoWindow,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub limit: Option<usize>,
+    pub group_by: Option<TopInfoGroupBy>,
+}
+
+impl From<TopInfoRequest> for request::TopInfoRequest {
+    fn from(top_info_request: TopInfoRequest) -> Self {
+        let TopInfoRequest {
+            window,
+            start,
+            end,
+            limit,
+            group_by,
+        } = top_info_request;
+        Self {
+            window: window.into(),
+            start,
+            end,
+            limit,
+            group_by: group_by.map(Into::into),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Logger writing errors and warnings to stderr and other records to stdout,
+/// as the backend printed before levels were configurable
+struct StdLogger;
+
+impl Log for StdLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        match record.level() {
+            Level::Error | Level::Warn => eprintln!("{}", record.args()),
+            _ => println!("{}", record.args()),
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StdLogger = StdLogger;
+
+/// Install the logger with `level`, which can be changed later by
+/// [`set_level`]
+pub fn init(level: LevelFilter) {
+    // Fails only if a logger is already installed, e.g., by another test
+    let _ = log::set_logger(&LOGGER);
+    set_level(level);
+}
+
+/// Change level of records logged from now on, e.g., on reload
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
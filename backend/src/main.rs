@@ -1,109 +1,1118 @@
+use actix_cors::Cors;
+use actix_rt::signal::unix::{signal, SignalKind};
 use actix_web::{
+    dev::{HttpResponseBuilder, Service, ServiceRequest, ServiceResponse},
     get,
-    web::{self, Query},
-    App, HttpResponse, HttpServer, Responder,
+    http::header,
+    middleware::{Compress, Condition},
+    post,
+    web::{self, Json, Query},
+    App, Error, HttpRequest, HttpResponse, HttpServer, Responder,
 };
-use std::time::Instant;
+use chrono::Utc;
+use futures::channel::mpsc;
+use futures::future::{ready, Either, Ready};
+use futures::stream::{self, StreamExt};
+use std::env;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
+mod aggregation;
+mod auth;
+mod background;
+mod cache;
 mod config;
+mod deadline;
+mod export;
+mod http_cache;
 mod interfaces;
+mod logger;
 mod model;
+mod openapi;
+mod popularity;
+mod rate_limit;
+mod regex_expansion;
+mod reload;
+mod search_after;
+mod search_pool;
+mod synonyms;
+mod tls;
+mod tracking;
+mod validation;
 
+use auth::{Authenticator, Role, API_KEY_HEADER};
+use background::BackgroundThreads;
+use config::HttpConfig;
+use deadline::Deadline;
+use http_cache::HttpCache;
 use interfaces::*;
 use model::UdiabModel;
+use popularity::Popularity;
+use rate_limit::RateLimiter;
+use reload::Reloader;
+use search_pool::SearchPool;
+use synonyms::Synonyms;
+use tls::CertificateResolver;
+use tracking::{Tracker, TrackingEvent};
+use validation::Validate;
 
+#[utoipa::path(
+    get,
+    path = "/api/key_hints",
+    params(KeyHintsRequest),
+    responses(
+        (status = 200, body = KeyHintsResponse),
+        (status = 400, description = "Empty, too long or malformed key"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
 #[get("/key_hints")]
 async fn get_key_hints(
     udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
     Query(key_hints_request): Query<KeyHintsRequest>,
 ) -> Result<impl Responder, UserError> {
+    key_hints_request.validate(&udiab_model.limits())?;
     let start = Instant::now();
-    let key_hints = udiab_model.get_key_hints(key_hints_request.key)?;
-    Ok(HttpResponse::Ok().content_type("application/json").body(
-        serde_json::to_string(&KeyHintsResponse {
-            key_hints,
-            duration: start.elapsed().as_millis(),
+    let deadline = Deadline::new(search_pool.timeout);
+    let key_hints = search_pool
+        .run(&deadline, move || {
+            udiab_model.get_key_hints(key_hints_request.key)
         })
-        .unwrap(),
-    ))
+        .await?;
+    Ok(response_builder(&deadline)
+        .content_type("application/json")
+        .body(
+            serde_json::to_string(&KeyHintsResponse {
+                key_hints,
+                duration: start.elapsed().as_millis(),
+            })
+            .unwrap(),
+        ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/retrieved_info",
+    params(RetrievedInfoRequest),
+    responses(
+        (status = 200, body = RetrievedInfoResponse),
+        (status = 400, description = "Request exceeds limits, malformed complex key, malformed cursor, or cursor of another sort method"),
+        (status = 409, description = "Index has changed since cursor is created"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
 #[get("/retrieved_info")]
 async fn get_retrieved_info(
     udiab_model: web::Data<UdiabModel>,
+    tracker: web::Data<Tracker>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
     Query(retrieve_info_request): Query<RetrievedInfoRequest>,
 ) -> Result<impl Responder, UserError> {
+    retrieved_info(
+        udiab_model,
+        &tracker,
+        &search_pool,
+        &rate_limiter,
+        retrieve_info_request,
+    )
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/retrieved_info",
+    params(v2::RetrievedInfoRequest),
+    responses(
+        (status = 200, body = RetrievedInfoResponse),
+        (status = 400, description = "Request exceeds limits, malformed complex key, malformed cursor, or cursor of another sort method"),
+        (status = 409, description = "Index has changed since cursor is created"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
+#[get("/retrieved_info")]
+async fn get_retrieved_info_v2(
+    udiab_model: web::Data<UdiabModel>,
+    tracker: web::Data<Tracker>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    Query(retrieve_info_request): Query<v2::RetrievedInfoRequest>,
+) -> Result<impl Responder, UserError> {
+    retrieved_info(
+        udiab_model,
+        &tracker,
+        &search_pool,
+        &rate_limiter,
+        retrieve_info_request.into(),
+    )
+    .await
+}
+
+/// Shared by all versions of retrieved info
+async fn retrieved_info(
+    udiab_model: web::Data<UdiabModel>,
+    tracker: &Tracker,
+    search_pool: &SearchPool,
+    rate_limiter: &RateLimiter,
+    retrieve_info_request: RetrievedInfoRequest,
+) -> Result<HttpResponse, UserError> {
+    retrieve_info_request.validate(&udiab_model.limits())?;
     let start = Instant::now();
     let RetrievedInfoRequest {
         key,
         advanced_search_options,
         offset,
+        cursor,
         page_size,
     } = retrieve_info_request;
-    let article_infos =
-        udiab_model.get_retrieved_info(key, advanced_search_options, offset, page_size)?;
-    Ok(HttpResponse::Ok().content_type("application/json").body(
-        serde_json::to_string(&RetrievedInfoResponse {
-            article_infos,
-            duration: start.elapsed().as_millis(),
+    let permit =
+        rate_limiter.acquire_expensive_if(advanced_search_options.search_method.is_expensive())?;
+    let deadline = Deadline::new(search_pool.timeout);
+    let searched_key = key.clone();
+    let (retrieved_info, offset) = search_pool
+        .run(&deadline, move || {
+            let _permit = permit;
+            match cursor {
+                Some(cursor) => Ok((
+                    udiab_model.get_retrieved_info_after(
+                        searched_key,
+                        advanced_search_options,
+                        &cursor,
+                        page_size,
+                    )?,
+                    None,
+                )),
+                None => Ok((
+                    udiab_model.get_retrieved_info(
+                        searched_key,
+                        advanced_search_options,
+                        offset,
+                        page_size,
+                    )?,
+                    Some(offset),
+                )),
+            }
         })
-        .unwrap(),
-    ))
+        .await?;
+    let impression_id = uuid::Uuid::new_v4().to_string();
+    tracker.record(&TrackingEvent::Impression {
+        impression_id: impression_id.clone(),
+        key,
+        advanced_search_options,
+        offset,
+        page_size,
+        urls: retrieved_info
+            .article_infos
+            .iter()
+            .map(|article_info| article_info.url.clone())
+            .collect(),
+        time: Utc::now(),
+    })?;
+    Ok(response_builder(&deadline)
+        .content_type("application/json")
+        .body(
+            serde_json::to_string(&RetrievedInfoResponse {
+                retrieved_info,
+                impression_id,
+                duration: start.elapsed().as_millis(),
+            })
+            .unwrap(),
+        ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/search",
+    request_body = SearchRequest,
+    responses(
+        (status = 200, body = RetrievedInfoResponse),
+        (status = 400, description = "Request exceeds limits, malformed complex key, or malformed cursor"),
+        (status = 409, description = "Index has changed since cursor is created"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
+#[post("/search")]
+async fn post_search(
+    udiab_model: web::Data<UdiabModel>,
+    tracker: web::Data<Tracker>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    Json(search_request): Json<SearchRequest>,
+) -> Result<impl Responder, UserError> {
+    search_request.validate(&udiab_model.limits())?;
+    let start = Instant::now();
+    let permit = rate_limiter.acquire_expensive_if(
+        search_request
+            .query
+            .as_ref()
+            .is_some_and(QueryClause::is_expensive),
+    )?;
+    let deadline = Deadline::new(search_pool.timeout);
+    let (retrieved_info, search_request) = search_pool
+        .run(&deadline, move || {
+            let _permit = permit;
+            Ok((udiab_model.search(&search_request)?, search_request))
+        })
+        .await?;
+    let impression_id = uuid::Uuid::new_v4().to_string();
+    tracker.record(&TrackingEvent::SearchImpression {
+        impression_id: impression_id.clone(),
+        request: search_request,
+        urls: retrieved_info
+            .article_infos
+            .iter()
+            .map(|article_info| article_info.url.clone())
+            .collect(),
+        time: Utc::now(),
+    })?;
+    Ok(response_builder(&deadline)
+        .content_type("application/json")
+        .body(
+            serde_json::to_string(&RetrievedInfoResponse {
+                retrieved_info,
+                impression_id,
+                duration: start.elapsed().as_millis(),
+            })
+            .unwrap(),
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/top_info",
+    params(TopInfoRequest),
+    responses(
+        (status = 200, body = TopArticleInfoResponse),
+        (status = 304, description = "Response tagged by If-None-Match is still fresh"),
+        (status = 400, description = "Custom window without valid range"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
+#[get("/top_info")]
+async fn get_top_info(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    http_config: web::Data<HttpConfig>,
+    request: HttpRequest,
+    Query(top_info_request): Query<TopInfoRequest>,
+) -> Result<impl Responder, UserError> {
+    top_info(
+        udiab_model,
+        &search_pool,
+        &http_config,
+        &request,
+        top_info_request,
+    )
+    .await
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v2/top_info",
+    params(v2::TopInfoRequest),
+    responses(
+        (status = 200, body = TopArticleInfoResponse),
+        (status = 304, description = "Response tagged by If-None-Match is still fresh"),
+        (status = 400, description = "Custom window without valid range"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
 #[get("/top_info")]
-async fn get_top_info(udiab_model: web::Data<UdiabModel>) -> Result<impl Responder, UserError> {
+async fn get_top_info_v2(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    http_config: web::Data<HttpConfig>,
+    request: HttpRequest,
+    Query(top_info_request): Query<v2::TopInfoRequest>,
+) -> Result<impl Responder, UserError> {
+    top_info(
+        udiab_model,
+        &search_pool,
+        &http_config,
+        &request,
+        top_info_request.into(),
+    )
+    .await
+}
+
+/// Shared by all versions of top info
+async fn top_info(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: &SearchPool,
+    http_config: &HttpConfig,
+    request: &HttpRequest,
+    top_info_request: TopInfoRequest,
+) -> Result<HttpResponse, UserError> {
+    let start = Instant::now();
+    let http_cache = HttpCache::new(udiab_model.generation(), http_config.cache_max_age);
+    if let Some(response) = not_modified(http_cache.as_ref(), request) {
+        return Ok(response);
+    }
+    let deadline = Deadline::new(search_pool.timeout);
+    let top_info = search_pool
+        .run(&deadline, move || {
+            udiab_model.get_top_info(top_info_request)
+        })
+        .await?;
+    Ok(cached_response_builder(&deadline, http_cache.as_ref())
+        .content_type("application/json")
+        .body(
+            serde_json::to_string(&TopArticleInfoResponse {
+                top_info,
+                duration: start.elapsed().as_millis(),
+            })
+            .unwrap(),
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/aggregations",
+    params(AggregationsRequest),
+    responses(
+        (status = 200, body = AggregationsResponse),
+        (status = 400, description = "Request exceeds limits, malformed complex key, or malformed likes boundaries"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
+#[get("/aggregations")]
+async fn get_aggregations(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    Query(aggregations_request): Query<AggregationsRequest>,
+) -> Result<impl Responder, UserError> {
+    aggregations(
+        udiab_model,
+        &search_pool,
+        &rate_limiter,
+        aggregations_request,
+    )
+    .await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/aggregations",
+    params(v2::AggregationsRequest),
+    responses(
+        (status = 200, body = AggregationsResponse),
+        (status = 400, description = "Request exceeds limits, malformed complex key, or malformed likes boundaries"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
+#[get("/aggregations")]
+async fn get_aggregations_v2(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    Query(aggregations_request): Query<v2::AggregationsRequest>,
+) -> Result<impl Responder, UserError> {
+    aggregations(
+        udiab_model,
+        &search_pool,
+        &rate_limiter,
+        aggregations_request.into(),
+    )
+    .await
+}
+
+/// Shared by all versions of aggregations
+async fn aggregations(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: &SearchPool,
+    rate_limiter: &RateLimiter,
+    aggregations_request: AggregationsRequest,
+) -> Result<HttpResponse, UserError> {
+    aggregations_request.validate(&udiab_model.limits())?;
+    let start = Instant::now();
+    let permit =
+        rate_limiter.acquire_expensive_if(aggregations_request.search_method.is_expensive())?;
+    let deadline = Deadline::new(search_pool.timeout);
+    let aggregations = search_pool
+        .run(&deadline, move || {
+            let _permit = permit;
+            udiab_model.get_aggregations(&aggregations_request)
+        })
+        .await?;
+    Ok(response_builder(&deadline)
+        .content_type("application/json")
+        .body(
+            serde_json::to_string(&AggregationsResponse {
+                aggregations,
+                duration: start.elapsed().as_millis(),
+            })
+            .unwrap(),
+        ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/explain",
+    params(ExplainRequest),
+    responses(
+        (status = 200, body = ExplainResponse),
+        (status = 400, description = "Request exceeds limits, or malformed complex key"),
+        (status = 404, description = "No article at URL"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded")
+    )
+)]
+#[get("/explain")]
+async fn get_explain(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    Query(explain_request): Query<ExplainRequest>,
+) -> Result<impl Responder, UserError> {
+    explain(udiab_model, &search_pool, &rate_limiter, explain_request).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v2/explain",
+    params(v2::ExplainRequest),
+    responses(
+        (status = 200, body = ExplainResponse),
+        (status = 400, description = "Request exceeds limits, or malformed complex key"),
+        (status = 404, description = "No article at URL"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded")
+    )
+)]
+#[get("/explain")]
+async fn get_explain_v2(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    Query(explain_request): Query<v2::ExplainRequest>,
+) -> Result<impl Responder, UserError> {
+    explain(
+        udiab_model,
+        &search_pool,
+        &rate_limiter,
+        explain_request.into(),
+    )
+    .await
+}
+
+/// Shared by all versions of explain
+async fn explain(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: &SearchPool,
+    rate_limiter: &RateLimiter,
+    explain_request: ExplainRequest,
+) -> Result<HttpResponse, UserError> {
+    explain_request.validate(&udiab_model.limits())?;
     let start = Instant::now();
-    let top_article_infos = udiab_model.get_top_info()?;
+    let permit = rate_limiter.acquire_expensive_if(explain_request.search_method.is_expensive())?;
+    let deadline = Deadline::new(search_pool.timeout);
+    let explain = search_pool
+        .run(&deadline, move || {
+            let _permit = permit;
+            udiab_model.explain(&explain_request)
+        })
+        .await?;
+    Ok(response_builder(&deadline)
+        .content_type("application/json")
+        .body(
+            serde_json::to_string(&ExplainResponse {
+                explain,
+                duration: start.elapsed().as_millis(),
+            })
+            .unwrap(),
+        ))
+}
+
+/// Header set if an export may miss some matches, see
+/// [`export::PreparedExport::partial`]
+const PARTIAL_RESULTS_HEADER: &str = "X-Partial-Results";
+
+#[utoipa::path(
+    get,
+    path = "/api/analyze",
+    params(AnalyzeRequest),
+    responses(
+        (status = 200, body = AnalyzeResponse),
+        (status = 400, description = "Too long text")
+    )
+)]
+#[get("/analyze")]
+async fn get_analyze(
+    udiab_model: web::Data<UdiabModel>,
+    Query(analyze_request): Query<AnalyzeRequest>,
+) -> Result<impl Responder, UserError> {
+    analyze_request.validate(&udiab_model.limits())?;
+    let start = Instant::now();
+    let analyses = udiab_model.get_analyses(&analyze_request.text)?;
     Ok(HttpResponse::Ok().content_type("application/json").body(
-        serde_json::to_string(&TopArticleInfoResponse {
-            top_article_infos,
+        serde_json::to_string(&AnalyzeResponse {
+            analyses,
             duration: start.elapsed().as_millis(),
         })
         .unwrap(),
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/export",
+    params(ExportRequest),
+    responses(
+        (status = 200, description = "Every matched article, streamed as CSV with a header row, \
+            or as JSON lines of `ExportedArticle`. `X-Partial-Results: true` is set if some \
+            matches may be missing", content_type = ["text/csv", "application/x-ndjson"]),
+        (status = 400, description = "Request exceeds limits, or malformed complex key"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries or exports exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded before export starts")
+    )
+)]
+#[get("/export")]
+async fn get_export(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    background_threads: web::Data<BackgroundThreads>,
+    Query(export_request): Query<ExportRequest>,
+) -> Result<HttpResponse, UserError> {
+    export_request.validate(&udiab_model.limits())?;
+    // Exports walk every match, so they are always expensive. Each also
+    // takes a thread while streaming, so that they are capped on their own
+    let permit = rate_limiter.acquire_expensive()?;
+    let export_permit = rate_limiter.acquire_export()?;
+    let format = export_request.format;
+    let include_body = export_request.include_body;
+    // Only building query is bounded by deadline, while streaming takes as
+    // long as client reads
+    let deadline = Deadline::new(search_pool.timeout);
+    let prepared_export = search_pool
+        .run(&deadline, move || {
+            udiab_model.prepare_export(&export_request)
+        })
+        .await?;
+    if deadline.is_expired() {
+        return Err(UserError::Timeout(
+            "Deadline exceeded before export starts".to_string(),
+        ));
+    }
+    let partial = prepared_export.partial;
+
+    let (sender, receiver) = mpsc::channel(export::EXPORT_BUFFER_SIZE);
+    background_threads.spawn(move |cancelled| {
+        let _permits = (permit, export_permit);
+        prepared_export.run(sender, cancelled);
+    });
+    let body = stream::once(ready(Ok(export::header(format, include_body)))).chain(receiver.map(
+        move |exported_article| {
+            exported_article.map(|exported_article| export::encode(format, &exported_article))
+        },
+    ));
+    let mut response = HttpResponse::Ok();
+    response.content_type(export::content_type(format));
+    if partial {
+        response.set_header(PARTIAL_RESULTS_HEADER, "true");
+    }
+    Ok(response.streaming(body))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/more_like_this",
+    params(MoreLikeThisRequest),
+    responses(
+        (status = 200, body = MoreLikeThisResponse),
+        (status = 304, description = "Response tagged by If-None-Match is still fresh"),
+        (status = 400, description = "Request exceeds limits, or document address out of range"),
+        (status = 429, description = "Rate limit or cap of concurrent expensive queries exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded, body contains partial results if any")
+    )
+)]
 #[get("/more_like_this")]
 async fn get_more_like_this(
     udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    rate_limiter: web::Data<RateLimiter>,
+    http_config: web::Data<HttpConfig>,
+    request: HttpRequest,
     Query(more_like_this_request): Query<MoreLikeThisRequest>,
 ) -> Result<impl Responder, UserError> {
+    more_like_this_request.validate(&udiab_model.limits())?;
     let start = Instant::now();
-    let more_like_this_article_infos = udiab_model.get_more_like_this(
-        more_like_this_request.address,
-        more_like_this_request.offset,
-        more_like_this_request.page_size,
-    )?;
-    Ok(HttpResponse::Ok().content_type("application/json").body(
-        serde_json::to_string(&MoreLikeThisResponse {
-            more_like_this_article_infos,
-            duration: start.elapsed().as_millis(),
+    let http_cache = HttpCache::new(udiab_model.generation(), http_config.cache_max_age);
+    if let Some(response) = not_modified(http_cache.as_ref(), &request) {
+        return Ok(response);
+    }
+    let permit = rate_limiter.acquire_expensive()?;
+    let deadline = Deadline::new(search_pool.timeout);
+    let more_like_this_article_infos = search_pool
+        .run(&deadline, move || {
+            let _permit = permit;
+            udiab_model.get_more_like_this(
+                more_like_this_request.address,
+                more_like_this_request.offset,
+                more_like_this_request.page_size,
+            )
+        })
+        .await?;
+    Ok(cached_response_builder(&deadline, http_cache.as_ref())
+        .content_type("application/json")
+        .body(
+            serde_json::to_string(&MoreLikeThisResponse {
+                more_like_this_article_infos,
+                duration: start.elapsed().as_millis(),
+            })
+            .unwrap(),
+        ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/click",
+    request_body = ClickRequest,
+    responses(
+        (status = 204, description = "Click is recorded"),
+        (status = 404, description = "No article at URL"),
+        (status = 429, description = "Click rate limit exceeded"),
+        (status = 503, description = "Too many searches are waiting"),
+        (status = 504, description = "Deadline exceeded")
+    )
+)]
+#[post("/click")]
+async fn post_click(
+    udiab_model: web::Data<UdiabModel>,
+    search_pool: web::Data<SearchPool>,
+    tracker: web::Data<Tracker>,
+    Json(click_request): Json<ClickRequest>,
+) -> Result<impl Responder, UserError> {
+    let ClickRequest {
+        impression_id,
+        key,
+        position,
+        url,
+    } = click_request;
+    // Otherwise anyone could push arbitrary URLs up the hot ranking
+    let deadline = Deadline::new(search_pool.timeout);
+    let contains_url = search_pool
+        .run(&deadline, {
+            let udiab_model = udiab_model.clone();
+            let url = url.clone();
+            move || udiab_model.contains_url(&url)
+        })
+        .await?;
+    if !contains_url {
+        return Err(UserError::NotFound(format!("No article at {}", url)));
+    }
+    udiab_model.popularity.record_click(&url);
+    tracker.record(&TrackingEvent::Click {
+        impression_id,
+        key,
+        position,
+        url,
+        time: Utc::now(),
+    })?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/cache_stats",
+    responses((status = 200, body = CacheStatsResponse))
+)]
+#[get("/cache_stats")]
+async fn get_cache_stats(udiab_model: web::Data<UdiabModel>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(serde_json::to_string(&udiab_model.cache_stats()).unwrap())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/reload",
+    responses(
+        (status = 204, description = "Latest commit of index is searched"),
+        (status = 401, description = "Missing or unknown API key"),
+        (status = 403, description = "API key is not of admin role")
+    ),
+    security(("api_key" = []))
+)]
+#[post("/reload")]
+async fn post_reload(udiab_model: web::Data<UdiabModel>) -> Result<impl Responder, UserError> {
+    udiab_model.reload()?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/synonyms/reload",
+    responses(
+        (status = 204, description = "Keys are expanded by the latest synonym dictionary"),
+        (status = 401, description = "Missing or unknown API key"),
+        (status = 403, description = "API key is not of admin role"),
+        (status = 500, description = "Synonyms failed to load, previous ones are kept")
+    ),
+    security(("api_key" = []))
+)]
+#[post("/synonyms/reload")]
+async fn post_reload_synonyms(
+    udiab_model: web::Data<UdiabModel>,
+) -> Result<impl Responder, UserError> {
+    let terms_count = udiab_model.synonyms.reload()?;
+    log::info!("Synonyms reloaded, {} terms", terms_count);
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/openapi.json")]
+async fn get_openapi() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("application/json")
+        .body(openapi::openapi_json())
+}
+
+/// Middleware rejecting requests failing `check`, e.g., clients exceeding
+/// rate limit or without required role
+fn guard<S, C>(
+    check: C,
+) -> impl Fn(ServiceRequest, &mut S) -> Either<S::Future, Ready<Result<ServiceResponse, Error>>> + Clone
+where
+    S: Service<Request = ServiceRequest, Response = ServiceResponse, Error = Error>,
+    C: Fn(&ServiceRequest) -> Result<(), UserError> + Clone,
+{
+    move |request, service| match check(&request) {
+        Ok(()) => Either::Left(service.call(request)),
+        Err(error) => Either::Right(ready(Ok(request.error_response(error)))),
+    }
+}
+
+/// Response with status 504 if deadline has expired, in which case the body
+/// contains partial results
+fn response_builder(deadline: &Deadline) -> HttpResponseBuilder {
+    if deadline.is_expired() {
+        HttpResponse::GatewayTimeout()
+    } else {
+        HttpResponse::Ok()
+    }
+}
+
+/// Like [`response_builder`], with HTTP caching headers unless results may
+/// be partial
+fn cached_response_builder(
+    deadline: &Deadline,
+    http_cache: Option<&HttpCache>,
+) -> HttpResponseBuilder {
+    let mut response = response_builder(deadline);
+    if let Some(http_cache) = http_cache.filter(|_| !deadline.is_expired()) {
+        http_cache.set_headers(&mut response);
+    }
+    response
+}
+
+/// Response with status 304 if client already has a fresh response
+fn not_modified(http_cache: Option<&HttpCache>, request: &HttpRequest) -> Option<HttpResponse> {
+    let http_cache = http_cache.filter(|http_cache| http_cache.is_fresh(request))?;
+    let mut response = HttpResponse::NotModified();
+    http_cache.set_headers(&mut response);
+    Some(response.finish())
+}
+
+/// CORS middleware allowing configured origins
+fn cors(http_config: &HttpConfig) -> Cors {
+    let cors = Cors::default()
+        .allowed_methods(vec!["GET", "POST"])
+        .allowed_headers(vec![header::CONTENT_TYPE.as_str(), API_KEY_HEADER])
+        .expose_headers(vec![header::ETAG, header::RETRY_AFTER])
+        .max_age(http_config.cors_max_age);
+    http_config
+        .cors_allowed_origins
+        .iter()
+        .fold(cors, |cors, origin| {
+            if origin == "*" {
+                cors.allow_any_origin()
+            } else {
+                cors.allowed_origin(origin)
+            }
         })
-        .unwrap(),
-    ))
 }
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let config = config::Config::retrieve_config();
+    let (config, config_source) = match config::Config::retrieve_config() {
+        Ok(retrieved) => retrieved,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    logger::init(config.log_level());
     let (index, project_document) = search_base::index(&config.index_store_directory).unwrap();
     let reader = index.reader().unwrap();
-
-    HttpServer::new(move || {
-        App::new().service(
-            web::scope("/api")
-                .app_data(web::Data::new(UdiabModel {
-                    reader: reader.clone(),
-                    project_document: project_document.clone(),
-                }))
-                .service(get_key_hints)
-                .service(get_retrieved_info)
-                .service(get_top_info)
-                .service(get_more_like_this),
+    let tracker = web::Data::new(Tracker::open(&config.tracking_log_path)?);
+    let popularity = Arc::new(
+        Popularity::open(
+            config.popularity.store_file(),
+            config.popularity.click_weight,
         )
+        .unwrap(),
+    );
+
+    // Cancelled and waited for after server stops
+    let background_threads = web::Data::new(BackgroundThreads::new());
+    {
+        let popularity = popularity.clone();
+        let reader = reader.clone();
+        let write_back_interval = Duration::from_secs(config.popularity.write_back_interval);
+        background_threads.spawn(move |cancelled| {
+            while !cancelled.load(Ordering::Relaxed) {
+                if let Err(error) = popularity.write_back(&reader.searcher(), project_document.url)
+                {
+                    log::error!("Popularity write back failed: {}", error);
+                }
+                // Unparked on shutdown
+                let next_write_back = Instant::now() + write_back_interval;
+                while !cancelled.load(Ordering::Relaxed) && Instant::now() < next_write_back {
+                    thread::park_timeout(next_write_back.saturating_duration_since(Instant::now()));
+                }
+            }
+        });
+    }
+
+    let synonyms =
+        Arc::new(Synonyms::open(config.synonyms.dictionary_file(), config.synonyms.boost).unwrap());
+
+    // Shared among workers, so that caches are shared
+    let udiab_model = web::Data::new(UdiabModel::new(
+        reader.clone(),
+        project_document,
+        popularity.clone(),
+        synonyms,
+        &config.cache,
+        config.limits.clone(),
+    ));
+    let search_pool = web::Data::new(SearchPool::new(
+        config.search_pool.threads,
+        config.search_pool.queue_size,
+        Duration::from_millis(config.search_pool.timeout),
+    ));
+    let rate_limiter = web::Data::new(RateLimiter::new(config.rate_limit.clone(), &config.auth));
+    let authenticator = web::Data::new(Authenticator::new(&config.auth));
+    let http_config = web::Data::new(config.http.clone());
+
+    {
+        let mut reloader = Reloader::new(
+            config_source,
+            config.clone(),
+            udiab_model.clone(),
+            rate_limiter.clone(),
+        );
+        actix_rt::spawn(async move {
+            let mut hangups = match signal(SignalKind::hangup()) {
+                Ok(hangups) => hangups,
+                Err(error) => {
+                    log::error!("Unable to listen to SIGHUP: {}", error);
+                    return;
+                }
+            };
+            while hangups.recv().await.is_some() {
+                match reloader.reload(env::vars()) {
+                    Ok(changes) => {
+                        log::info!("Config reloaded, {} settings changed", changes.len());
+                        for change in changes {
+                            log::info!("  {}", change);
+                        }
+                    }
+                    Err(error) => log::error!("Config reload failed, nothing applied. {}", error),
+                }
+            }
+        });
+    }
+
+    // Load certificate before anything is served, so that a broken one
+    // fails startup instead of handshakes
+    let tls_server_config = if config.tls.enabled {
+        match CertificateResolver::new(&config.tls) {
+            Ok(resolver) => Some(resolver.server_config()),
+            Err(error) => {
+                log::error!("Unable to load TLS certificate: {}", error);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        None
+    };
+
+    // Actix drains in-flight requests on SIGTERM, within shutdown timeout.
+    // Background threads get whatever is left of it afterwards
+    let shutdown_started = Arc::new(OnceLock::new());
+    {
+        let shutdown_started = shutdown_started.clone();
+        actix_rt::spawn(async move {
+            let mut terminations = match signal(SignalKind::terminate()) {
+                Ok(terminations) => terminations,
+                Err(error) => {
+                    log::error!("Unable to listen to SIGTERM: {}", error);
+                    return;
+                }
+            };
+            if terminations.recv().await.is_some() {
+                let _ = shutdown_started.set(Instant::now());
+            }
+        });
+    }
+    let app_tracker = tracker.clone();
+    let app_background_threads = background_threads.clone();
+    let server = HttpServer::new(move || {
+        let rate_limit = {
+            let rate_limiter = rate_limiter.clone();
+            move |request: &ServiceRequest| {
+                if request.path().ends_with("/click") {
+                    rate_limiter.check_click_request(request)
+                } else {
+                    rate_limiter.check_request(request)
+                }
+            }
+        };
+        let authorize = |role| {
+            let authenticator = authenticator.clone();
+            move |request: &ServiceRequest| authenticator.check_request(request, role)
+        };
+        // Middlewares registered later run first, so that rate limit applies
+        // before authentication
+        App::new()
+            // Same-origin browsers send `Origin` as well, which CORS middleware
+            // would reject if no origin is allowed
+            .wrap(Condition::new(
+                !http_config.cors_allowed_origins.is_empty(),
+                cors(&http_config),
+            ))
+            .wrap(Compress::default())
+            // Compression is negotiated by `Accept-Encoding`, which is ignored
+            // if compression is disabled
+            .wrap_fn({
+                let compress = http_config.compress;
+                move |mut request: ServiceRequest, service| {
+                    if !compress {
+                        request.headers_mut().remove(header::ACCEPT_ENCODING);
+                    }
+                    service.call(request)
+                }
+            })
+            .service(
+                web::scope("/api/admin")
+                    .app_data(udiab_model.clone())
+                    .wrap_fn(guard(authorize(Role::Admin)))
+                    .wrap_fn(guard(rate_limit.clone()))
+                    .service(post_reload)
+                    .service(post_reload_synonyms),
+            )
+            // Registered before v1 like admin, otherwise `/api` scope would
+            // swallow it.
+            // Only endpoints with enums in requests differ from v1.
+            .service(
+                web::scope("/api/v2")
+                    .app_data(udiab_model.clone())
+                    .app_data(app_tracker.clone())
+                    .app_data(app_background_threads.clone())
+                    .app_data(search_pool.clone())
+                    .app_data(rate_limiter.clone())
+                    .app_data(http_config.clone())
+                    .wrap_fn(guard(authorize(Role::Read)))
+                    .wrap_fn(guard(rate_limit.clone()))
+                    .service(get_key_hints)
+                    .service(get_retrieved_info_v2)
+                    .service(post_search)
+                    .service(get_export)
+                    .service(get_aggregations_v2)
+                    .service(get_explain_v2)
+                    .service(get_analyze)
+                    .service(get_top_info_v2)
+                    .service(get_more_like_this)
+                    .service(post_click)
+                    .service(get_cache_stats),
+            )
+            .service(
+                web::scope("/api")
+                    .app_data(udiab_model.clone())
+                    .app_data(app_tracker.clone())
+                    .app_data(app_background_threads.clone())
+                    .app_data(search_pool.clone())
+                    .app_data(rate_limiter.clone())
+                    .app_data(http_config.clone())
+                    .wrap_fn(guard(authorize(Role::Read)))
+                    .wrap_fn(guard(rate_limit.clone()))
+                    .service(get_key_hints)
+                    .service(get_retrieved_info)
+                    .service(post_search)
+                    .service(get_export)
+                    .service(get_aggregations)
+                    .service(get_explain)
+                    .service(get_analyze)
+                    .service(get_top_info)
+                    .service(get_more_like_this)
+                    .service(post_click)
+                    .service(get_cache_stats)
+                    .service(get_openapi),
+            )
     })
-    .bind((config.host.as_str(), config.port))?
-    .run()
-    .await
+    .shutdown_timeout(config.shutdown_timeout);
+    let server = match tls_server_config {
+        Some(tls_server_config) => {
+            server.bind_rustls((config.host.as_str(), config.port), tls_server_config)?
+        }
+        None => server.bind((config.host.as_str(), config.port))?,
+    }
+    .run();
+
+    if config.tls.enabled && config.tls.redirect_http {
+        let https_port = config.port;
+        let redirect_server = HttpServer::new(move || {
+            App::new().default_service(web::route().to(move |request: HttpRequest| {
+                ready(tls::redirect_to_https(&request, https_port))
+            }))
+        })
+        .workers(1)
+        .bind((config.host.as_str(), config.tls.redirect_port))?
+        .shutdown_timeout(config.shutdown_timeout)
+        .run();
+        futures::future::try_join(server, redirect_server).await?;
+    } else {
+        server.await?;
+    }
+
+    // Exports whose clients are gone stop at next article, and the
+    // popularity thread stops sleeping
+    let shutdown_deadline = *shutdown_started.get().unwrap_or(&Instant::now())
+        + Duration::from_secs(config.shutdown_timeout);
+    let unfinished =
+        background_threads.shutdown(shutdown_deadline.saturating_duration_since(Instant::now()));
+    if unfinished > 0 {
+        log::warn!("{} background threads did not finish in time", unfinished);
+    }
+    tracker.close();
+    // Persist clicks recorded since last write back
+    if let Err(error) = popularity.write_back(&reader.searcher(), project_document.url) {
+        log::error!("Popularity write back failed: {}", error);
+    }
+    Ok(())
 }
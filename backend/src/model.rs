@@ -1,21 +1,38 @@
+use super::aggregation::{DateHistogramCollector, RangeHistogramCollector};
+use super::cache::QueryCache;
+use super::config::{CacheConfig, LimitsConfig};
+use super::deadline::{with_deadline, with_segment_deadline, Deadline};
+use super::export::PreparedExport;
 use super::interfaces::{
-    AdvanceSearchOptions, MoreLikeThisArticleInfo, SearchMethod, SearchSortBy, SearchedArticleInfo,
-    Snippet, TopArticleInfo, UdiabDocAddress, UserError, MAX_BODY_LENGTH, MAX_CODE_LENGTH,
-    MAX_KEY_HINTS_COUNT, MAX_TITLE_LENGTH, TOP_ARTICLE_INFOS_COUNT,
+    AdvanceSearchOptions, Aggregations, AggregationsRequest, Analysis, AnalyzedToken, BoolClause,
+    CacheStatsResponse, DateBucket, Explain, ExplainRequest, ExplainedToken, ExplanationNode,
+    ExportRequest, FieldTokens, LikesBucket, MatchClause, MoreLikeThisArticleInfo, QueryClause,
+    RetrievedInfo, SearchField, SearchFilter, SearchMethod, SearchRequest, SearchSortBy,
+    SearchedArticleInfo, Snippet, SortField, SortSpec, SourceCount, TopArticleGroup,
+    TopArticleInfo, TopInfo, TopInfoRequest, UdiabDocAddress, UserError,
+    DEFAULT_TOP_ARTICLE_INFOS_COUNT, DEFAULT_TOP_SOURCES_COUNT, MAX_BODY_LENGTH, MAX_CODE_LENGTH,
+    MAX_KEY_HINTS_COUNT, MAX_TITLE_LENGTH, MAX_TOP_ARTICLE_INFOS_COUNT, MAX_TOP_SOURCES_COUNT,
 };
-use cang_jie::CANG_JIE;
-use chrono::{Duration, Utc};
+use super::popularity::Popularity;
+use super::regex_expansion::{expand_regex, RegexExpansion};
+use super::search_after::{sort_fingerprint, SearchAfterCollector, SearchCursor, SortKey};
+use super::synonyms::Synonyms;
+use chrono::{TimeZone, Utc};
 use search_base::ProjectDocument;
+use std::collections::hash_map::DefaultHasher;
 use std::convert::Into;
+use std::hash::{Hash, Hasher};
 use std::ops::Bound;
-use tantivy::collector::TopDocs;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration as StdDuration, Instant};
+use tantivy::collector::{Count, FacetCollector, TopDocs};
 use tantivy::{
     query::{
-        BooleanQuery, MoreLikeThisQuery, PhraseQuery, Query, QueryParser, RangeQuery, RegexQuery,
-        TermQuery,
+        AllQuery, BooleanQuery, BoostQuery, MoreLikeThisQuery, Occur, PhraseQuery, Query,
+        QueryParser, QueryParserError, RangeQuery, TermQuery,
     },
-    schema::{IndexRecordOption, Type},
-    DocAddress, IndexReader, Searcher, SnippetGenerator, Term,
+    schema::{Facet, Field, FieldType, IndexRecordOption, Type},
+    DocAddress, DocSet, Index, IndexReader, Searcher, SnippetGenerator, Term,
 };
 
 /// Model for UDIAB project
@@ -27,6 +44,101 @@ pub struct UdiabModel {
     ///
     /// Presented because we would use `get_field` with string comparison otherwise
     pub project_document: ProjectDocument,
+    /// Internal popularity counter, blended with scraped likes for hot scoring
+    pub popularity: Arc<Popularity>,
+    /// Synonyms expanding keys of every search method
+    pub synonyms: Arc<Synonyms>,
+    /// Cache of [`UdiabModel::get_key_hints`]
+    pub key_hints_cache: QueryCache<String, Vec<Snippet>>,
+    /// Cache of [`UdiabModel::get_retrieved_info`]
+    pub retrieved_info_cache: QueryCache<RetrievedInfoCacheKey, RetrievedInfo>,
+    /// Cache of [`UdiabModel::get_top_info`]
+    pub top_info_cache: QueryCache<TopInfoRequest, TopInfo>,
+    /// Limits of requests and query expansion, see [`UdiabModel::limits`]
+    limits: RwLock<LimitsConfig>,
+}
+
+/// Cache key of retrieved info: normalized key, options, offset, cursor and page size
+type RetrievedInfoCacheKey = (String, AdvanceSearchOptions, usize, Option<String>, usize);
+
+/// Normalize key for caching, by collapsing whitespaces
+///
+/// Regex keys are kept as they are, since whitespaces are meaningful.
+fn normalize_key(key: &str, search_method: SearchMethod) -> String {
+    match search_method {
+        SearchMethod::Regex => key.to_string(),
+        _ => key.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// Generation of searcher, which changes once index is reloaded with
+/// segments added, merged or deleted from.
+pub fn searcher_generation(searcher: &Searcher) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for segment_reader in searcher.segment_readers() {
+        segment_reader.segment_id().hash(&mut hasher);
+        segment_reader.num_deleted_docs().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// Generation of cursors of `sort` in `searcher`, which changes once index
+/// is reloaded, or popularity is written back if sorted by hot
+fn cursor_generation(searcher: &Searcher, sort: &[SortSpec], popularity: &Popularity) -> u64 {
+    let generation = searcher_generation(searcher);
+    if sort.iter().any(|sort_spec| sort_spec.by == SortField::Hot) {
+        generation ^ popularity.version()
+    } else {
+        generation
+    }
+}
+
+/// Decode cursor, checking it is created with the same sort list and the
+/// same cursor generation, see [`cursor_generation`]
+fn decode_cursor(
+    cursor: &str,
+    sort: &[SortSpec],
+    generation: u64,
+) -> Result<SearchCursor, UserError> {
+    let cursor = SearchCursor::decode(cursor)?;
+    if cursor.sort != sort_fingerprint(sort) {
+        return Err(UserError::BadRequest(
+            "Cursor is created with another sort method".to_string(),
+        ));
+    }
+    if cursor.generation != generation {
+        return Err(UserError::Conflict(
+            "Index has changed since cursor is created, please search again".to_string(),
+        ));
+    }
+    Ok(cursor)
+}
+
+/// Error of key failing to parse as a complex query, which is the user's
+/// fault
+fn query_parser_error(error: QueryParserError) -> UserError {
+    UserError::BadRequest(format!("Invalid query: {}", error))
+}
+
+/// Tokens of `text` by tokenizer registered as `tokenizer_name` in index
+pub fn analyze(
+    index: &Index,
+    tokenizer_name: &str,
+    text: &str,
+) -> Result<Vec<AnalyzedToken>, UserError> {
+    let tokenizer = index.tokenizers().get(tokenizer_name).ok_or_else(|| {
+        UserError::Unexpected(format!("Unable to find tokenizer {}", tokenizer_name))
+    })?;
+    let mut tokens = vec![];
+    tokenizer.token_stream(text).process(&mut |token| {
+        tokens.push(AnalyzedToken {
+            text: token.text.clone(),
+            offset_from: token.offset_from,
+            offset_to: token.offset_to,
+            position: token.position,
+        })
+    });
+    Ok(tokens)
 }
 
 /// Convert DocAddress to SearchedArticleInfo
@@ -72,10 +184,8 @@ fn from_doc_address_to_searched_article_info(
     let mut time = None;
     for field_value in doc.field_values() {
         match field_value.field() {
-            field if field == project_document.title => {
-                if title_snippet.fragments.is_empty() {
-                    title = field_value.value().text()
-                }
+            field if field == project_document.title && title_snippet.fragments.is_empty() => {
+                title = field_value.value().text()
             }
             field if field == project_document.url => url = field_value.value().text(),
             field if field == project_document.likes => likes = field_value.value().u64_value(),
@@ -90,7 +200,7 @@ fn from_doc_address_to_searched_article_info(
                 title_snippet.highlighted_positions,
             )
         } else {
-            return Err(UserError::Unexpected(format!("Can't find title field")));
+            return Err(UserError::Unexpected("Can't find title field".to_string()));
         }
     } else {
         title_snippet
@@ -98,17 +208,17 @@ fn from_doc_address_to_searched_article_info(
     let url = if let Some(url) = url {
         url.to_string()
     } else {
-        return Err(UserError::Unexpected(format!("Can't find url field")));
+        return Err(UserError::Unexpected("Can't find url field".to_string()));
     };
     let likes = if let Some(likes) = likes {
         likes
     } else {
-        return Err(UserError::Unexpected(format!("Can't find likes field")));
+        return Err(UserError::Unexpected("Can't find likes field".to_string()));
     };
     let time = if let Some(time) = time {
         *time
     } else {
-        return Err(UserError::Unexpected(format!("Can't find time field")));
+        return Err(UserError::Unexpected("Can't find time field".to_string()));
     };
     Ok(SearchedArticleInfo {
         title_snippet,
@@ -122,36 +232,141 @@ fn from_doc_address_to_searched_article_info(
 }
 
 impl UdiabModel {
+    /// Create model with empty caches configured by `cache_config`
+    pub fn new(
+        reader: IndexReader,
+        project_document: ProjectDocument,
+        popularity: Arc<Popularity>,
+        synonyms: Arc<Synonyms>,
+        cache_config: &CacheConfig,
+        limits: LimitsConfig,
+    ) -> Self {
+        let ttl = StdDuration::from_secs(cache_config.ttl);
+        Self {
+            reader,
+            project_document,
+            popularity,
+            synonyms,
+            key_hints_cache: QueryCache::new(cache_config.capacity, ttl),
+            retrieved_info_cache: QueryCache::new(cache_config.capacity, ttl),
+            top_info_cache: QueryCache::new(cache_config.capacity, ttl),
+            limits: RwLock::new(limits),
+        }
+    }
+
+    /// Generation of cached results, which changes once index is reloaded,
+    /// popularity is written back or synonyms are reloaded
+    fn cache_generation(&self, searcher: &Searcher) -> u64 {
+        searcher_generation(searcher)
+            ^ self.popularity.version()
+            ^ self.synonyms.version().rotate_left(32)
+    }
+
+    /// Reload searchers, so that the latest commit of index is searched
+    /// without waiting for the reader to notice it
+    pub fn reload(&self) -> Result<(), UserError> {
+        self.reader
+            .reload()
+            .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })
+    }
+
+    /// Limits of requests and query expansion
+    pub fn limits(&self) -> LimitsConfig {
+        self.limits
+            .read()
+            .map_or_else(|_| LimitsConfig::default(), |limits| limits.clone())
+    }
+
+    pub fn set_limits(&self, limits: LimitsConfig) {
+        if let Ok(mut current_limits) = self.limits.write() {
+            *current_limits = limits;
+        }
+    }
+
+    /// Change capacity and TTL of all caches
+    pub fn resize_caches(&self, cache_config: &CacheConfig) {
+        let ttl = StdDuration::from_secs(cache_config.ttl);
+        self.key_hints_cache.resize(cache_config.capacity, ttl);
+        self.retrieved_info_cache.resize(cache_config.capacity, ttl);
+        self.top_info_cache.resize(cache_config.capacity, ttl);
+    }
+
+    /// Generation of current searcher, see [`UdiabModel::cache_generation`]
+    pub fn generation(&self) -> u64 {
+        self.cache_generation(&self.reader.searcher())
+    }
+
+    /// Statistics of all caches
+    pub fn cache_stats(&self) -> CacheStatsResponse {
+        CacheStatsResponse {
+            key_hints: self.key_hints_cache.stats(),
+            retrieved_info: self.retrieved_info_cache.stats(),
+            top_info: self.top_info_cache.stats(),
+        }
+    }
+
     /// Get key hints
     ///
-    /// For now, we just start a query for title field.
+    /// For now, we just start a query for title field, falling back to pinyin
+    /// of title if nothing matches.
     ///
     /// The returned snippets count will not exceed [`MAX_KEY_HINTS_COUNT`]
     pub fn get_key_hints(&self, key: String) -> Result<Vec<Snippet>, UserError> {
         let searcher = self.reader.searcher();
+        self.key_hints_cache.get_or_try_insert_with(
+            normalize_key(&key, SearchMethod::Naive),
+            self.cache_generation(&searcher),
+            || self.search_key_hints(&searcher, &key),
+        )
+    }
 
+    fn search_key_hints(&self, searcher: &Searcher, key: &str) -> Result<Vec<Snippet>, UserError> {
         let query_parser =
-            QueryParser::for_index(&searcher.index(), vec![self.project_document.title]);
+            QueryParser::for_index(searcher.index(), vec![self.project_document.title]);
 
-        let query = query_parser.parse_query(&key).map_err(|tantivy_error| {
-            UserError::UnexpectedTantivy {
-                tantivy_error: tantivy_error.into(),
-            }
-        })?;
+        let query = query_parser.parse_query(key).map_err(query_parser_error)?;
 
-        let mut snippet_generator =
-            SnippetGenerator::create(&searcher, &query, self.project_document.title)
-                .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
+        let snippets =
+            self.search_title_snippets(searcher, &*query, self.project_document.title)?;
+        // Pinyin typed with IME off, e.g., `shujuku` or `sjk` for `数据库`.
+        // Highlighted by pinyin tokenizer, whose offsets are of title
+        match search_base::pinyin_terms(key) {
+            Some(terms) if snippets.is_empty() => self.search_title_snippets(
+                searcher,
+                &*self.pinyin_query(&terms),
+                self.project_document.title_pinyin,
+            ),
+            _ => Ok(snippets),
+        }
+    }
+
+    /// Titles of top articles matching query, highlighted by terms of query
+    /// in `highlighted_field`
+    fn search_title_snippets(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        highlighted_field: Field,
+    ) -> Result<Vec<Snippet>, UserError> {
+        let mut snippet_generator = SnippetGenerator::create(searcher, query, highlighted_field)
+            .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
         snippet_generator.set_max_num_chars(MAX_TITLE_LENGTH);
         let snippets = searcher
-            .search(&query, &TopDocs::with_limit(MAX_KEY_HINTS_COUNT))
+            .search(
+                query,
+                &with_segment_deadline(TopDocs::with_limit(MAX_KEY_HINTS_COUNT)),
+            )
             .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?
             .into_iter()
             .map(|(_, doc_address)| {
                 let doc = searcher
                     .doc(doc_address)
                     .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
-                let snippet = snippet_generator.snippet_from_doc(&doc);
+                let title = doc
+                    .get_first(self.project_document.title)
+                    .and_then(|title| title.text())
+                    .unwrap_or_default();
+                let snippet = snippet_generator.snippet(title);
                 Ok(Snippet::new(
                     snippet.fragments().to_string(),
                     snippet.highlighted().to_vec(),
@@ -160,57 +375,403 @@ impl UdiabModel {
             .collect::<Result<Vec<_>, _>>()?;
         Ok(snippets)
     }
-    /// Get searched article info
+
+    /// Phrase query of pinyin of title, see [`search_base::pinyin_terms`]
+    fn pinyin_query(&self, terms: &[String]) -> Box<dyn Query> {
+        let terms = terms
+            .iter()
+            .map(|term| Term::from_field_text(self.project_document.title_pinyin, term))
+            .collect::<Vec<_>>();
+        match &terms[..] {
+            [term] => Box::new(TermQuery::new(term.clone(), IndexRecordOption::WithFreqs)),
+            _ => Box::new(PhraseQuery::new(terms)),
+        }
+    }
+
+    /// Query searched instead of key if key matches no title, i.e., pinyin
+    /// of title if key is entirely full pinyin syllables typed with IME off,
+    /// e.g., `shujuku` for `数据库`
+    ///
+    /// Initials are not accepted as in key hints, so that an English word
+    /// without hits is rarely taken as pinyin. Used by `/retrieved_info` and
+    /// endpoints describing the same hits, e.g., aggregations.
+    fn pinyin_fallback_query(
+        &self,
+        key: &str,
+        search_field: SearchField,
+    ) -> Option<Box<dyn Query>> {
+        if search_field != SearchField::Title {
+            return None;
+        }
+        search_base::full_pinyin_terms(key).map(|terms| self.pinyin_query(&terms))
+    }
+
+    /// Get searched article info, paginated by offset
     pub fn get_retrieved_info(
         &self,
         key: String,
         advanced_search_options: AdvanceSearchOptions,
         offset: usize,
         page_size: usize,
-    ) -> Result<Vec<SearchedArticleInfo>, UserError> {
+    ) -> Result<RetrievedInfo, UserError> {
+        self.get_retrieved_info_paginated(key, advanced_search_options, offset, None, page_size)
+    }
+
+    /// Get searched article info after the cursor returned by previous page
+    ///
+    /// The cursor is rejected if it is created with another sort method, if
+    /// index has been reloaded since then, or if sorted by hot and
+    /// popularity has changed since then.
+    pub fn get_retrieved_info_after(
+        &self,
+        key: String,
+        advanced_search_options: AdvanceSearchOptions,
+        cursor: &str,
+        page_size: usize,
+    ) -> Result<RetrievedInfo, UserError> {
+        self.get_retrieved_info_paginated(
+            key,
+            advanced_search_options,
+            0,
+            Some(cursor.to_string()),
+            page_size,
+        )
+    }
+
+    fn get_retrieved_info_paginated(
+        &self,
+        key: String,
+        advanced_search_options: AdvanceSearchOptions,
+        offset: usize,
+        cursor: Option<String>,
+        page_size: usize,
+    ) -> Result<RetrievedInfo, UserError> {
+        let searcher = self.reader.searcher();
+        let generation = self.cache_generation(&searcher);
+        let sort = [SortSpec::from(advanced_search_options.sort_by)];
+        let cursor_generation = cursor_generation(&searcher, &sort, &self.popularity);
+        let decoded_cursor = cursor
+            .as_deref()
+            .map(|cursor| decode_cursor(cursor, &sort, cursor_generation))
+            .transpose()?;
+        let cache_key = (
+            normalize_key(&key, advanced_search_options.search_method),
+            advanced_search_options,
+            offset,
+            cursor,
+            page_size,
+        );
+        if let Some(retrieved_info) = self.retrieved_info_cache.get(&cache_key, generation) {
+            return Ok(retrieved_info);
+        }
+        let (query, partial) = self.key_query(
+            &searcher,
+            &key,
+            &advanced_search_options
+                .search_field
+                .tantivy_fields(self.project_document),
+            advanced_search_options.search_method,
+        )?;
+        let (mut retrieved_info, count) = self.search_retrieved_info(
+            &searcher,
+            cursor_generation,
+            &*query,
+            &sort,
+            offset,
+            decoded_cursor.clone(),
+            page_size,
+            partial,
+        )?;
+        if count == 0 {
+            if let Some(pinyin_query) =
+                self.pinyin_fallback_query(&key, advanced_search_options.search_field)
+            {
+                (retrieved_info, _) = self.search_retrieved_info(
+                    &searcher,
+                    cursor_generation,
+                    &*pinyin_query,
+                    &sort,
+                    offset,
+                    decoded_cursor,
+                    page_size,
+                    partial,
+                )?;
+            }
+        }
+        // Partial results depend on how busy the backend is, do not cache them
+        if !retrieved_info.partial {
+            self.retrieved_info_cache
+                .insert(cache_key, generation, retrieved_info.clone());
+        }
+        Ok(retrieved_info)
+    }
+
+    /// Search articles with query DSL
+    ///
+    /// Results are not cached, since DSL queries are seldom repeated.
+    pub fn search(&self, search_request: &SearchRequest) -> Result<RetrievedInfo, UserError> {
+        let SearchRequest {
+            query,
+            filters,
+            sort,
+            pagination,
+        } = search_request;
+        let searcher = self.reader.searcher();
+        let sort = if sort.is_empty() {
+            vec![SortSpec::from(SearchSortBy::Relevance)]
+        } else {
+            sort.clone()
+        };
+        let cursor_generation = cursor_generation(&searcher, &sort, &self.popularity);
+        let decoded_cursor = pagination
+            .cursor
+            .as_deref()
+            .map(|cursor| decode_cursor(cursor, &sort, cursor_generation))
+            .transpose()?;
+
+        let (query, partial) = self.filtered_query(&searcher, query.as_ref(), filters)?;
+        let (retrieved_info, _) = self.search_retrieved_info(
+            &searcher,
+            cursor_generation,
+            &*query,
+            &sort,
+            if decoded_cursor.is_some() {
+                0
+            } else {
+                pagination.offset
+            },
+            decoded_cursor,
+            pagination.page_size,
+            partial,
+        )?;
+        Ok(retrieved_info)
+    }
+
+    /// Prepare export of all articles matching request, see
+    /// [`PreparedExport::run`]
+    pub fn prepare_export(
+        &self,
+        export_request: &ExportRequest,
+    ) -> Result<PreparedExport, UserError> {
+        let searcher = self.reader.searcher();
+        let (query, partial) = self.filtered_query(
+            &searcher,
+            export_request.query().as_ref(),
+            &export_request.filters(),
+        )?;
+        PreparedExport::new(
+            &searcher,
+            &*query,
+            self.project_document,
+            export_request.include_body,
+            partial,
+        )
+    }
+
+    /// Build query of an optional DSL clause restricted by filters, and
+    /// whether the query is partial
+    fn filtered_query(
+        &self,
+        searcher: &Searcher,
+        query: Option<&QueryClause>,
+        filters: &[SearchFilter],
+    ) -> Result<(Box<dyn Query>, bool), UserError> {
+        let (query, partial) = match query {
+            Some(query) => self.clause_query(searcher, query)?,
+            None => (Box::new(AllQuery) as Box<dyn Query>, false),
+        };
+        if filters.is_empty() {
+            return Ok((query, partial));
+        }
+        let mut subqueries = vec![(Occur::Must, query)];
+        for filter in filters {
+            // Zero boost, so that filters do not affect relevance
+            subqueries.push((
+                Occur::Must,
+                Box::new(BoostQuery::new(self.filter_query(filter)?, 0.0)),
+            ));
+        }
+        Ok((Box::new(BooleanQuery::new(subqueries)), partial))
+    }
+
+    /// Build query of a DSL clause, and whether the query is partial
+    fn clause_query(
+        &self,
+        searcher: &Searcher,
+        clause: &QueryClause,
+    ) -> Result<(Box<dyn Query>, bool), UserError> {
+        match clause {
+            QueryClause::Match(MatchClause { key, field, method }) => self.key_query(
+                searcher,
+                key,
+                &field.tantivy_fields(self.project_document),
+                (*method).into(),
+            ),
+            QueryClause::Bool(BoolClause {
+                must,
+                should,
+                must_not,
+            }) => {
+                let mut subqueries = vec![];
+                let mut partial = false;
+                for (occur, clauses) in [
+                    (Occur::Must, must),
+                    (Occur::Should, should),
+                    (Occur::MustNot, must_not),
+                ] {
+                    for clause in clauses {
+                        let (subquery, subquery_partial) = self.clause_query(searcher, clause)?;
+                        subqueries.push((occur, subquery));
+                        partial |= subquery_partial;
+                    }
+                }
+                // A boolean query with only `mustNot` clauses matches nothing
+                // in tantivy, while we expect it to exclude from all articles
+                if must.is_empty() && should.is_empty() {
+                    subqueries.push((Occur::Must, Box::new(AllQuery)));
+                }
+                Ok((Box::new(BooleanQuery::new(subqueries)), partial))
+            }
+            QueryClause::MatchAll {} => Ok((Box::new(AllQuery), false)),
+        }
+    }
+
+    /// Build query of a DSL filter
+    fn filter_query(&self, filter: &SearchFilter) -> Result<Box<dyn Query>, UserError> {
+        let ProjectDocument {
+            time: time_field,
+            source: source_field,
+            language: language_field,
+            ..
+        } = self.project_document;
+        let query: Box<dyn Query> = match filter {
+            SearchFilter::Time { gte, lt } => {
+                let bound = |timestamp: &Option<i64>, inclusive: bool| match timestamp {
+                    Some(timestamp) => {
+                        let term =
+                            Term::from_field_date(time_field, &Utc.timestamp_millis(*timestamp));
+                        if inclusive {
+                            Bound::Included(term)
+                        } else {
+                            Bound::Excluded(term)
+                        }
+                    }
+                    None => Bound::Unbounded,
+                };
+                Box::new(RangeQuery::new_term_bounds(
+                    time_field,
+                    Type::Date,
+                    &bound(gte, true),
+                    &bound(lt, false),
+                ))
+            }
+            SearchFilter::Source(source) | SearchFilter::Language(source)
+                if source.is_empty() || source.contains('/') =>
+            {
+                return Err(UserError::BadRequest(format!(
+                    "Invalid filter value: {:?}",
+                    source
+                )))
+            }
+            SearchFilter::Source(source) => Box::new(TermQuery::new(
+                Term::from_facet(source_field, &Facet::from_path(vec![source.to_lowercase()])),
+                IndexRecordOption::Basic,
+            )),
+            SearchFilter::Language(language) => Box::new(TermQuery::new(
+                Term::from_facet(
+                    language_field,
+                    &Facet::from_path(vec![language.to_lowercase()]),
+                ),
+                IndexRecordOption::Basic,
+            )),
+        };
+        Ok(query)
+    }
+
+    /// Build query of key in given fields with search method, and whether
+    /// the query is partial (see [`expand_regex`])
+    ///
+    /// Variants of key expanded by synonyms (see [`Synonyms::expand`]) are
+    /// added as should clauses with reduced boost. Key and its variants share
+    /// one time budget of regex expansion.
+    fn key_query(
+        &self,
+        searcher: &Searcher,
+        key: &str,
+        searched_fields: &[Field],
+        search_method: SearchMethod,
+    ) -> Result<(Box<dyn Query>, bool), UserError> {
+        let regex_expires_at =
+            Instant::now() + StdDuration::from_millis(self.limits().regex_time_budget);
+        let (query, mut partial) = self.method_query(
+            searcher,
+            key,
+            searched_fields,
+            search_method,
+            regex_expires_at,
+        )?;
+        let variants = self.synonyms.expand(key, search_method);
+        if variants.is_empty() {
+            return Ok((query, partial));
+        }
+        let boost = self.synonyms.boost();
+        let mut subqueries = vec![(Occur::Should, query)];
+        for variant in variants {
+            // Variants are best effort, e.g., a synonym may break syntax of
+            // complex key
+            if let Ok((variant_query, variant_partial)) = self.method_query(
+                searcher,
+                &variant,
+                searched_fields,
+                search_method,
+                regex_expires_at,
+            ) {
+                partial |= variant_partial;
+                subqueries.push((
+                    Occur::Should,
+                    Box::new(BoostQuery::new(variant_query, boost)),
+                ));
+            }
+        }
+        Ok((Box::new(BooleanQuery::new(subqueries)), partial))
+    }
+
+    /// Build query of key as it is, see [`UdiabModel::key_query`]
+    fn method_query(
+        &self,
+        searcher: &Searcher,
+        key: &str,
+        searched_fields: &[Field],
+        search_method: SearchMethod,
+        regex_expires_at: Instant,
+    ) -> Result<(Box<dyn Query>, bool), UserError> {
         let ProjectDocument {
             title: title_field,
             body: body_field,
             code: code_field,
-            likes: likes_field,
-            time: time_field,
             ..
         } = self.project_document;
-        let searcher = self.reader.searcher();
-
-        let searched_fields = advanced_search_options
-            .search_field
-            .tantivy_fields(self.project_document);
 
         let query =
-            match advanced_search_options.search_method {
+            match search_method {
                 SearchMethod::Regex => {
-                    let mut subqueries = searched_fields
-                        .iter()
-                        .map(|field| {
-                            let regex_query = RegexQuery::from_pattern(&key, *field)?;
-                            Ok(Box::new(regex_query) as Box<dyn Query>)
-                        })
-                        .collect::<Result<Vec<_>, tantivy::error::TantivyError>>()
-                        .map_err(|tantivy_error| UserError::UnexpectedTantivy {
-                            tantivy_error: tantivy_error.into(),
-                        })?;
-                    if let &[_] = &subqueries[..] {
-                        subqueries.pop().unwrap()
-                    } else {
-                        Box::new(BooleanQuery::union(subqueries))
-                    }
+                    let limits = self.limits();
+                    let RegexExpansion { query, partial } = expand_regex(
+                        searcher,
+                        searched_fields,
+                        key,
+                        limits.max_regex_expanded_terms,
+                        limits.max_regex_automaton_size,
+                        regex_expires_at,
+                    )?;
+                    return Ok((query, partial));
                 }
                 SearchMethod::Complex => {
                     // When user uses complex search, we just use the built-in parse query
                     // to build a query, and NO lenient mode
                     let query_parser =
-                        QueryParser::for_index(&searcher.index(), searched_fields.clone());
-                    query_parser.parse_query(&key).map_err(|tantivy_error| {
-                        UserError::UnexpectedTantivy {
-                            tantivy_error: tantivy_error.into(),
-                        }
-                    })?
+                        QueryParser::for_index(searcher.index(), searched_fields.to_vec());
+                    query_parser.parse_query(key).map_err(query_parser_error)?
                 }
                 SearchMethod::Naive => {
                     // When user does not use complex search, we use corresponding
@@ -223,25 +784,21 @@ impl UdiabModel {
                     // we can only extract such logic from its source code
 
                     // For All and Title
-                    let chinese_tokenizer = self
-                        .reader
-                        .searcher()
+                    let text_tokenizer = searcher
                         .index()
                         .tokenizers()
-                        .get(CANG_JIE)
-                        .ok_or(UserError::Unexpected(format!(
-                            "Unable to find CANG JIE tokenizer"
-                        )))?;
+                        .get(search_base::TEXT_TOKENIZER)
+                        .ok_or(UserError::Unexpected(
+                            "Unable to find text tokenizer".to_string(),
+                        ))?;
                     // For Code
-                    let trivial_tokenizer = self
-                        .reader
-                        .searcher()
+                    let trivial_tokenizer = searcher
                         .index()
                         .tokenizers()
-                        .get("naivetokenizer")
-                        .ok_or(UserError::Unexpected(format!(
-                            "Unable to find simple tokenizer"
-                        )))?;
+                        .get(search_base::CODE_TOKENIZER)
+                        .ok_or(UserError::Unexpected(
+                            "Unable to find simple tokenizer".to_string(),
+                        ))?;
                     let mut subqueries =
                         searched_fields
                             .iter()
@@ -249,13 +806,13 @@ impl UdiabModel {
                                 let mut terms = vec![];
                                 let tokenizer = match field {
                                     field if *field == title_field || *field == body_field => {
-                                        &chinese_tokenizer
+                                        &text_tokenizer
                                     }
                                     field if *field == code_field => &trivial_tokenizer,
                                     // unreachable
                                     _ => &trivial_tokenizer,
                                 };
-                                let mut token_stream = tokenizer.token_stream(&key);
+                                let mut token_stream = tokenizer.token_stream(key);
                                 token_stream.process(&mut |token| {
                                     let term = Term::from_field_text(*field, &token.text);
                                     terms.push((token.position, term));
@@ -279,107 +836,428 @@ impl UdiabModel {
                     }
                 }
             };
+        Ok((query, false))
+    }
+
+    /// Search a page of articles, with cursor of `cursor_generation` to the
+    /// next page, together with count of all matches
+    #[allow(clippy::too_many_arguments)]
+    fn search_retrieved_info(
+        &self,
+        searcher: &Searcher,
+        cursor_generation: u64,
+        query: &dyn Query,
+        sort: &[SortSpec],
+        offset: usize,
+        cursor: Option<SearchCursor>,
+        page_size: usize,
+        partial: bool,
+    ) -> Result<(RetrievedInfo, usize), UserError> {
+        let ProjectDocument {
+            title: title_field,
+            body: body_field,
+            code: code_field,
+            likes: likes_field,
+            time: time_field,
+            ..
+        } = self.project_document;
 
-        let mut title_snippet_generator = SnippetGenerator::create(&searcher, &query, title_field)
+        let mut title_snippet_generator = SnippetGenerator::create(searcher, query, title_field)
             .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
         title_snippet_generator.set_max_num_chars(MAX_TITLE_LENGTH);
-        let mut body_snippet_generator = SnippetGenerator::create(&searcher, &query, body_field)
+        let mut body_snippet_generator = SnippetGenerator::create(searcher, query, body_field)
             .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
         body_snippet_generator.set_max_num_chars(MAX_BODY_LENGTH);
-        let mut code_snippet_generator = SnippetGenerator::create(&searcher, &query, code_field)
+        let mut code_snippet_generator = SnippetGenerator::create(searcher, query, code_field)
             .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
         code_snippet_generator.set_max_num_chars(MAX_CODE_LENGTH);
 
-        let search_collector = TopDocs::with_limit(page_size).and_offset(offset);
-        let searched_article_infos = match advanced_search_options.sort_by {
-            SearchSortBy::Hot => {
-                let search_collector = search_collector.order_by_u64_field(likes_field);
-                searcher
-                    .search(&query, &search_collector)
-                    .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?
-                    .into_iter()
-                    .map(|(_, doc_address)| {
-                        from_doc_address_to_searched_article_info(
-                            &searcher,
-                            &self.project_document,
-                            &title_snippet_generator,
-                            &body_snippet_generator,
-                            &code_snippet_generator,
-                            doc_address,
-                        )
-                    })
-                    .collect::<Result<Vec<_>, _>>()
+        let sort_keys = sort
+            .iter()
+            .map(|sort_spec| {
+                let sort_key = match sort_spec.by {
+                    SortField::Hot => SortKey::Hot(self.popularity.hot_scorer(likes_field)),
+                    // If the field is a `FAST` field but not a `u64` field, we can still
+                    // get a monotonic `u64`-representation (ie. the order is still correct)
+                    // of the requested field type.
+                    SortField::Time => SortKey::FastField(time_field),
+                    SortField::Likes => SortKey::FastField(likes_field),
+                    SortField::Relevance => SortKey::Relevance,
+                };
+                (sort_key, sort_spec.order)
+            })
+            .collect();
+        let mut search_collector = SearchAfterCollector::new(sort_keys, page_size, offset);
+        if let Some(cursor) = &cursor {
+            search_collector = search_collector.after(cursor);
+        }
+        let (count, hits) = searcher
+            .search(query, &with_deadline((Count, search_collector)))
+            .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
+        let partial = partial || Deadline::current_expired();
+        let next_cursor = match hits.last() {
+            Some((values, doc_address)) if hits.len() == page_size => Some(
+                SearchCursor {
+                    sort: sort_fingerprint(sort),
+                    values: values.clone(),
+                    segment_ord: doc_address.segment_ord,
+                    doc_id: doc_address.doc_id,
+                    generation: cursor_generation,
+                }
+                .encode(),
+            ),
+            _ => None,
+        };
+        let article_infos = hits
+            .into_iter()
+            .map(|(_, doc_address)| {
+                from_doc_address_to_searched_article_info(
+                    searcher,
+                    &self.project_document,
+                    &title_snippet_generator,
+                    &body_snippet_generator,
+                    &code_snippet_generator,
+                    doc_address,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((
+            RetrievedInfo {
+                article_infos,
+                next_cursor,
+                partial,
+            },
+            count,
+        ))
+    }
+
+    /// Get the top hot articles in a time window, optionally grouped by
+    /// source site or code language.
+    ///
+    /// Results are cached per request, and the cache is dropped once index is
+    /// reloaded.
+    pub fn get_top_info(&self, top_info_request: TopInfoRequest) -> Result<TopInfo, UserError> {
+        let time_field = self.project_document.time;
+        let searcher = self.reader.searcher();
+        let generation = self.cache_generation(&searcher);
+        if let Some(top_info) = self.top_info_cache.get(&top_info_request, generation) {
+            return Ok(top_info);
+        }
+
+        let (start_time, end_time) = match top_info_request.window.duration() {
+            Some(duration) => {
+                let current_time = Utc::now();
+                (current_time - duration, current_time)
             }
-            SearchSortBy::Time => {
-                // If the field is a `FAST` field but not a `u64` field,
-                // search will return successfully but it will return returns
-                // a monotonic `u64`-representation (ie. the order is still correct) of the requested field type.
-                let search_collector = search_collector.order_by_u64_field(time_field);
-                searcher
-                    .search(&query, &search_collector)
-                    .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?
-                    .into_iter()
-                    .map(|(_, doc_address)| {
-                        from_doc_address_to_searched_article_info(
+            None => match (top_info_request.start, top_info_request.end) {
+                (Some(start), Some(end)) if start < end => {
+                    (Utc.timestamp_millis(start), Utc.timestamp_millis(end))
+                }
+                _ => {
+                    return Err(UserError::BadRequest(
+                        "Custom window requires start and end, and start must be before end"
+                            .to_string(),
+                    ))
+                }
+            },
+        };
+        let limit = top_info_request
+            .limit
+            .unwrap_or(DEFAULT_TOP_ARTICLE_INFOS_COUNT)
+            .min(MAX_TOP_ARTICLE_INFOS_COUNT);
+        let docs_in_window: Box<dyn Query> = Box::new(RangeQuery::new_term_bounds(
+            time_field,
+            Type::Date,
+            &Bound::Included(Term::from_field_date(time_field, &start_time)),
+            &Bound::Excluded(Term::from_field_date(time_field, &end_time)),
+        ));
+
+        let top_article_infos = self.get_top_article_infos(&searcher, &*docs_in_window, limit)?;
+        let groups = if let Some(group_by) = top_info_request.group_by {
+            let group_field = group_by.tantivy_field(self.project_document);
+            let mut facet_collector = FacetCollector::for_field(group_field);
+            facet_collector.add_facet(Facet::root());
+            let facet_counts = searcher
+                .search(&*docs_in_window, &with_deadline(facet_collector))
+                .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
+            let mut groups = facet_counts
+                .get(Facet::root())
+                .map(|(facet, count)| (facet.clone(), count))
+                .collect::<Vec<_>>();
+            groups.sort_by(|(_, count), (_, other_count)| other_count.cmp(count));
+            let groups = groups
+                .into_iter()
+                .map(|(facet, count)| {
+                    let docs_in_group = BooleanQuery::intersection(vec![
+                        docs_in_window.box_clone(),
+                        Box::new(TermQuery::new(
+                            Term::from_facet(group_field, &facet),
+                            IndexRecordOption::Basic,
+                        )),
+                    ]);
+                    Ok(TopArticleGroup {
+                        name: facet.to_path().join("/"),
+                        count,
+                        top_article_infos: self.get_top_article_infos(
                             &searcher,
-                            &self.project_document,
-                            &title_snippet_generator,
-                            &body_snippet_generator,
-                            &code_snippet_generator,
-                            doc_address,
-                        )
+                            &docs_in_group,
+                            limit,
+                        )?,
                     })
-                    .collect::<Result<Vec<_>, _>>()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Some(groups)
+        } else {
+            None
+        };
+
+        let top_info = TopInfo {
+            top_article_infos,
+            groups,
+        };
+        self.top_info_cache
+            .insert(top_info_request, generation, top_info.clone());
+        Ok(top_info)
+    }
+
+    /// Aggregate articles matching key: date histogram, likes buckets and
+    /// top sources
+    ///
+    /// Articles are matched as by `/retrieved_info`, including the fallback
+    /// of [`UdiabModel::pinyin_fallback_query`].
+    ///
+    /// Results are not cached, like [`UdiabModel::search`].
+    pub fn get_aggregations(
+        &self,
+        aggregations_request: &AggregationsRequest,
+    ) -> Result<Aggregations, UserError> {
+        let ProjectDocument {
+            time: time_field,
+            likes: likes_field,
+            source: source_field,
+            ..
+        } = self.project_document;
+        let likes_boundaries = aggregations_request
+            .likes_boundaries()
+            .map_err(UserError::BadRequest)?;
+        let top_sources_count = aggregations_request
+            .top_sources
+            .unwrap_or(DEFAULT_TOP_SOURCES_COUNT)
+            .min(MAX_TOP_SOURCES_COUNT);
+        let searcher = self.reader.searcher();
+        let (query, partial) = self.key_query(
+            &searcher,
+            &aggregations_request.key,
+            &aggregations_request
+                .search_field
+                .tantivy_fields(self.project_document),
+            aggregations_request.search_method,
+        )?;
+
+        let aggregate = |query: &dyn Query| {
+            let mut source_collector = FacetCollector::for_field(source_field);
+            source_collector.add_facet(Facet::root());
+            searcher
+                .search(
+                    query,
+                    &with_deadline((
+                        Count,
+                        DateHistogramCollector::new(time_field, aggregations_request.interval),
+                        RangeHistogramCollector::new(likes_field, likes_boundaries.clone()),
+                        source_collector,
+                    )),
+                )
+                .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })
+        };
+        let mut aggregated = aggregate(&*query)?;
+        if aggregated.0 == 0 {
+            if let Some(pinyin_query) = self
+                .pinyin_fallback_query(&aggregations_request.key, aggregations_request.search_field)
+            {
+                aggregated = aggregate(&*pinyin_query)?;
             }
-            SearchSortBy::Relevance => {
-                // search_collector is scored by relevance by default
-                searcher
-                    .search(&query, &search_collector)
-                    .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?
+        }
+        let (total, date_buckets, likes_counts, source_counts) = aggregated;
+        let partial = partial || Deadline::current_expired();
+
+        let date_histogram = date_buckets
+            .into_iter()
+            .map(|(start, count)| DateBucket {
+                start: Utc.timestamp(start, 0),
+                count,
+            })
+            .collect();
+        let likes_buckets = likes_counts
+            .into_iter()
+            .enumerate()
+            .map(|(index, count)| LikesBucket {
+                from: if index == 0 {
+                    0
+                } else {
+                    likes_boundaries[index - 1]
+                },
+                to: likes_boundaries.get(index).copied(),
+                count,
+            })
+            .collect();
+        let mut top_sources = source_counts
+            .get(Facet::root())
+            .map(|(facet, count)| SourceCount {
+                source: facet.to_path().join("/"),
+                count,
+            })
+            .collect::<Vec<_>>();
+        top_sources.sort_by(|source_count, other_source_count| {
+            other_source_count
+                .count
+                .cmp(&source_count.count)
+                .then_with(|| source_count.source.cmp(&other_source_count.source))
+        });
+        top_sources.truncate(top_sources_count);
+        Ok(Aggregations {
+            total,
+            date_histogram,
+            likes_buckets,
+            top_sources,
+            partial,
+        })
+    }
+
+    /// Address of the article at `url`, if any
+    fn find_url(&self, searcher: &Searcher, url: &str) -> Result<Option<DocAddress>, UserError> {
+        Ok(searcher
+            .search(
+                &TermQuery::new(
+                    Term::from_field_text(self.project_document.url, url),
+                    IndexRecordOption::Basic,
+                ),
+                &TopDocs::with_limit(1),
+            )
+            .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?
+            .pop()
+            .map(|(_, doc_address)| doc_address))
+    }
+
+    /// Whether there is an article at `url` in index
+    pub fn contains_url(&self, url: &str) -> Result<bool, UserError> {
+        Ok(self.find_url(&self.reader.searcher(), url)?.is_some())
+    }
+
+    /// Explain relevance score of the article at `url` for key, together
+    /// with how key is tokenized in each searched field
+    pub fn explain(&self, explain_request: &ExplainRequest) -> Result<Explain, UserError> {
+        let searcher = self.reader.searcher();
+        let doc_address = self
+            .find_url(&searcher, &explain_request.url)?
+            .ok_or_else(|| UserError::NotFound(format!("No article at {}", explain_request.url)))?;
+        let searched_fields = explain_request
+            .search_field
+            .tantivy_fields(self.project_document);
+        let (query, partial) = self.key_query(
+            &searcher,
+            &explain_request.key,
+            &searched_fields,
+            explain_request.search_method,
+        )?;
+
+        // Tantivy fails to explain an article not matching query
+        let explanation = query
+            .explain(&searcher, doc_address)
+            .ok()
+            .map(|explanation| {
+                serde_json::to_value(&explanation)
+                    .and_then(serde_json::from_value::<ExplanationNode>)
+                    .map_err(|error| UserError::Unexpected(error.to_string()))
+            })
+            .transpose()?;
+
+        let segment_reader = searcher.segment_reader(doc_address.segment_ord);
+        let schema = searcher.schema();
+        let field_tokens = searched_fields
+            .iter()
+            .map(|&field| {
+                let field_entry = schema.get_field_entry(field);
+                let tokenizer = match field_entry.field_type() {
+                    FieldType::Str(text_options) => text_options
+                        .get_indexing_options()
+                        .map(|indexing_options| indexing_options.tokenizer().to_string()),
+                    _ => None,
+                }
+                .unwrap_or_default();
+                let inverted_index = segment_reader
+                    .inverted_index(field)
+                    .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
+                let tokens = analyze(searcher.index(), &tokenizer, &explain_request.key)?
                     .into_iter()
-                    .map(|(_, doc_address)| {
-                        from_doc_address_to_searched_article_info(
-                            &searcher,
-                            &self.project_document,
-                            &title_snippet_generator,
-                            &body_snippet_generator,
-                            &code_snippet_generator,
-                            doc_address,
-                        )
+                    .map(|token| {
+                        let term = Term::from_field_text(field, &token.text);
+                        let matched = inverted_index
+                            .read_postings(&term, IndexRecordOption::Basic)
+                            .map_err(|io_error| UserError::UnexpectedTantivy {
+                                tantivy_error: io_error.into(),
+                            })?
+                            .is_some_and(|mut postings| {
+                                postings.seek(doc_address.doc_id) == doc_address.doc_id
+                            });
+                        Ok(ExplainedToken { token, matched })
                     })
-                    .collect::<Result<Vec<_>, _>>()
-            }
-        }?;
+                    .collect::<Result<Vec<_>, UserError>>()?;
+                Ok(FieldTokens {
+                    field: field_entry.name().to_string(),
+                    tokenizer,
+                    tokens,
+                })
+            })
+            .collect::<Result<Vec<_>, UserError>>()?;
 
-        Ok(searched_article_infos)
+        Ok(Explain {
+            matched: explanation.is_some(),
+            explanation,
+            field_tokens,
+            partial,
+        })
     }
 
-    /// Get the top 10 hot articles from yesterday to today
-    pub fn get_top_info(&self) -> Result<Vec<TopArticleInfo>, UserError> {
+    /// Tokens of `text` by every tokenizer registered by
+    /// [`search_base::index`], see [`search_base::tokenizer_names`]
+    pub fn get_analyses(&self, text: &str) -> Result<Vec<Analysis>, UserError> {
+        let searcher = self.reader.searcher();
+        search_base::tokenizer_names()
+            .into_iter()
+            .map(|tokenizer| {
+                Ok(Analysis {
+                    tokenizer: tokenizer.to_string(),
+                    tokens: analyze(searcher.index(), tokenizer, text)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Get the top hot articles matching query
+    fn get_top_article_infos(
+        &self,
+        searcher: &Searcher,
+        query: &dyn Query,
+        limit: usize,
+    ) -> Result<Vec<TopArticleInfo>, UserError> {
         let ProjectDocument {
             title: title_field,
             url: url_field,
             likes: likes_field,
-            time: time_field,
             ..
         } = self.project_document;
 
-        let searcher = self.reader.searcher();
-        let current_time = Utc::now();
-        let start_time = current_time - Duration::weeks(4);
-        let docs_in_today = RangeQuery::new_term_bounds(
-            time_field,
-            Type::Date,
-            &Bound::Included(Term::from_field_date(time_field, &start_time)),
-            &Bound::Excluded(Term::from_field_date(time_field, &current_time)),
-        );
-        let today_top_docs = searcher
+        let top_docs = searcher
             .search(
-                &docs_in_today,
-                &TopDocs::with_limit(TOP_ARTICLE_INFOS_COUNT).order_by_u64_field(likes_field),
+                query,
+                &with_deadline(
+                    TopDocs::with_limit(limit)
+                        .custom_score(self.popularity.hot_scorer(likes_field)),
+                ),
             )
             .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
-        let top_article_infos = today_top_docs
+        top_docs
             .into_iter()
             .map(|(_, top_doc_address)| {
                 let top_doc = searcher
@@ -399,22 +1277,21 @@ impl UdiabModel {
                 let title = if let Some(title) = title {
                     title.to_string()
                 } else {
-                    return Err(UserError::Unexpected(format!("Can't find title field")));
+                    return Err(UserError::Unexpected("Can't find title field".to_string()));
                 };
                 let url = if let Some(url) = url {
                     url.to_string()
                 } else {
-                    return Err(UserError::Unexpected(format!("Can't find url field")));
+                    return Err(UserError::Unexpected("Can't find url field".to_string()));
                 };
                 let likes = if let Some(likes) = likes {
                     likes
                 } else {
-                    return Err(UserError::Unexpected(format!("Can't find likes field")));
+                    return Err(UserError::Unexpected("Can't find likes field".to_string()));
                 };
                 Ok(TopArticleInfo { title, url, likes })
             })
-            .collect::<Result<Vec<_>, _>>()?;
-        Ok(top_article_infos)
+            .collect::<Result<Vec<_>, _>>()
     }
 
     /// Get articles more like this article
@@ -434,10 +1311,20 @@ impl UdiabModel {
         } = self.project_document;
         let doc_address: DocAddress = address.into();
         let searcher = self.reader.searcher();
+        // Tantivy panics on an address out of range
+        let in_range = searcher
+            .segment_readers()
+            .get(doc_address.segment_ord as usize)
+            .is_some_and(|segment_reader| doc_address.doc_id < segment_reader.max_doc());
+        if !in_range {
+            return Err(UserError::BadRequest(
+                "Document address is out of range".to_string(),
+            ));
+        }
         let query = MoreLikeThisQuery::builder().with_document(doc_address);
         let search_collector = TopDocs::with_limit(page_size).and_offset(offset);
         let docs_more_like_this = searcher
-            .search(&query, &search_collector)
+            .search(&query, &with_segment_deadline(search_collector))
             .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
         let more_like_this_article_infos = docs_more_like_this
             .into_iter()
@@ -463,27 +1350,27 @@ impl UdiabModel {
                 let title = if let Some(title) = title {
                     title.chars().take(MAX_TITLE_LENGTH).collect::<String>()
                 } else {
-                    return Err(UserError::Unexpected(format!("Can't find title field")));
+                    return Err(UserError::Unexpected("Can't find title field".to_string()));
                 };
                 let body = if let Some(body) = body {
                     body.chars().take(MAX_BODY_LENGTH).collect::<String>()
                 } else {
-                    return Err(UserError::Unexpected(format!("Can't find body field")));
+                    return Err(UserError::Unexpected("Can't find body field".to_string()));
                 };
                 let url = if let Some(url) = url {
                     url.to_string()
                 } else {
-                    return Err(UserError::Unexpected(format!("Can't find url field")));
+                    return Err(UserError::Unexpected("Can't find url field".to_string()));
                 };
                 let likes = if let Some(likes) = likes {
                     likes
                 } else {
-                    return Err(UserError::Unexpected(format!("Can't find likes field")));
+                    return Err(UserError::Unexpected("Can't find likes field".to_string()));
                 };
                 let time = if let Some(time) = time {
                     *time
                 } else {
-                    return Err(UserError::Unexpected(format!("Can't find time field")));
+                    return Err(UserError::Unexpected("Can't find time field".to_string()));
                 };
                 Ok(MoreLikeThisArticleInfo {
                     url,
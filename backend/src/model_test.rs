@@ -1,5 +1,8 @@
 use super::*;
+use crate::config::{CacheConfig, LimitsConfig};
 use crate::interfaces::*;
+use crate::popularity::Popularity;
+use crate::synonyms::Synonyms;
 use chrono::{serde::ts_milliseconds::deserialize as from_milli_ts, DateTime, Utc};
 use search_base::ProjectDocument;
 use serde::Deserialize;
@@ -19,6 +22,10 @@ struct UdiabModelDocument {
 }
 
 fn create_data(manipulator: fn(UdiabModel)) {
+    create_data_from("test_data/main_data.json", manipulator)
+}
+
+fn create_data_from(path: &str, manipulator: fn(UdiabModel)) {
     let dir = tempfile::tempdir().unwrap();
     let dir_path = dir.path();
 
@@ -26,15 +33,18 @@ fn create_data(manipulator: fn(UdiabModel)) {
     let ProjectDocument {
         url: url_field,
         title: title_field,
+        title_pinyin: title_pinyin_field,
         body: body_field,
         code: code_field,
         likes: likes_field,
         time: time_field,
+        source: source_field,
+        language: language_field,
     } = project_document;
     let mut index_writer = index.writer(100_000_000).unwrap();
 
     let documents: Vec<UdiabModelDocument> =
-        serde_json::from_str(&fs::read_to_string("test_data/main_data.json").unwrap()).unwrap();
+        serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
     for document in documents {
         let UdiabModelDocument {
             url,
@@ -44,20 +54,30 @@ fn create_data(manipulator: fn(UdiabModel)) {
             likes,
             time,
         } = document;
-        index_writer.add_document(doc! {
+        let mut document = doc! {
+            source_field => search_base::source_facet(&url),
             url_field => url,
+            title_pinyin_field => title.clone(),
             title_field => title,
             body_field => body,
-            code_field => code,
             likes_field => likes,
             time_field => time,
-        });
+        };
+        if let Some(language) = search_base::language_facet(&code) {
+            document.add_facet(language_field, language);
+        }
+        document.add_text(code_field, &code);
+        index_writer.add_document(document);
     }
     index_writer.commit().unwrap();
-    let udiab_model = UdiabModel {
-        reader: index.reader().unwrap(),
+    let udiab_model = UdiabModel::new(
+        index.reader().unwrap(),
         project_document,
-    };
+        Arc::new(Popularity::open(None, 10).unwrap()),
+        Arc::new(Synonyms::open(None, 0.5).unwrap()),
+        &CacheConfig::default(),
+        LimitsConfig::default(),
+    );
     manipulator(udiab_model);
 }
 
@@ -76,7 +96,11 @@ fn test_get_retrieved_info_by_relevance() {
         );
         assert!(got.is_ok());
         let got = got.unwrap();
-        let got_url = got.into_iter().map(|info| info.url).collect::<Vec<_>>();
+        let got_url = got
+            .article_infos
+            .into_iter()
+            .map(|info| info.url)
+            .collect::<Vec<_>>();
         let expect_url = vec!["url3".to_string(), "url1".to_string(), "url2".to_string()];
         assert_eq!(got_url, expect_url);
     });
@@ -98,7 +122,11 @@ fn test_get_retrieved_info_by_time() {
         println!("{:?}", got);
         assert!(got.is_ok());
         let got = got.unwrap();
-        let got_url = got.into_iter().map(|info| info.url).collect::<Vec<_>>();
+        let got_url = got
+            .article_infos
+            .into_iter()
+            .map(|info| info.url)
+            .collect::<Vec<_>>();
         let expect_url = vec!["url1".to_string(), "url2".to_string(), "url3".to_string()];
         assert_eq!(got_url, expect_url);
     });
@@ -119,8 +147,860 @@ fn test_get_retrieved_info_by_hot() {
         );
         assert!(got.is_ok());
         let got = got.unwrap();
-        let got_url = got.into_iter().map(|info| info.url).collect::<Vec<_>>();
+        let got_url = got
+            .article_infos
+            .into_iter()
+            .map(|info| info.url)
+            .collect::<Vec<_>>();
+        let expect_url = vec!["url2".to_string(), "url1".to_string(), "url3".to_string()];
+        assert_eq!(got_url, expect_url);
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_by_hot_with_clicks() {
+    create_data(|udiab_model| {
+        for _ in 0..5 {
+            udiab_model.popularity.record_click("url3");
+        }
+        udiab_model
+            .popularity
+            .write_back(
+                &udiab_model.reader.searcher(),
+                udiab_model.project_document.url,
+            )
+            .unwrap();
+        let got = udiab_model.get_retrieved_info(
+            "标题".to_string(),
+            AdvanceSearchOptions {
+                search_field: SearchField::All,
+                sort_by: SearchSortBy::Hot,
+                search_method: SearchMethod::Naive,
+            },
+            0,
+            10,
+        );
+        assert!(got.is_ok());
+        let got = got.unwrap();
+        let got_url = got
+            .article_infos
+            .into_iter()
+            .map(|info| info.url)
+            .collect::<Vec<_>>();
+        let expect_url = vec!["url3".to_string(), "url2".to_string(), "url1".to_string()];
+        assert_eq!(got_url, expect_url);
+    });
+}
+
+#[test]
+fn test_write_back_bumps_version_only_on_change() {
+    create_data(|udiab_model| {
+        let write_back = || {
+            udiab_model
+                .popularity
+                .write_back(
+                    &udiab_model.reader.searcher(),
+                    udiab_model.project_document.url,
+                )
+                .unwrap()
+        };
+        let version = udiab_model.popularity.version();
+        write_back();
+        assert_eq!(udiab_model.popularity.version(), version);
+        udiab_model.popularity.record_click("url3");
+        write_back();
+        let version = udiab_model.popularity.version();
+        write_back();
+        assert_eq!(udiab_model.popularity.version(), version);
+    });
+}
+
+#[test]
+fn test_write_back_persists_counts() {
+    create_data(|udiab_model| {
+        let dir = tempfile::tempdir().unwrap();
+        let store_path = dir.path().join("popularity.json");
+        let popularity = Popularity::open(Some(store_path.clone()), 10).unwrap();
+        popularity.record_click("url3");
+        popularity
+            .write_back(
+                &udiab_model.reader.searcher(),
+                udiab_model.project_document.url,
+            )
+            .unwrap();
+        assert!(!dir.path().join("popularity.json.tmp").exists());
+        let popularity = Popularity::open(Some(store_path), 10).unwrap();
+        popularity
+            .write_back(
+                &udiab_model.reader.searcher(),
+                udiab_model.project_document.url,
+            )
+            .unwrap();
+        assert_eq!(popularity.version(), 1);
+    });
+}
+
+#[test]
+fn test_contains_url() {
+    create_data(|udiab_model| {
+        assert!(udiab_model.contains_url("url1").unwrap());
+        assert!(!udiab_model.contains_url("https://example.com").unwrap());
+    });
+}
+
+#[test]
+fn test_get_top_info_in_custom_window() {
+    create_data(|udiab_model| {
+        let got = udiab_model.get_top_info(TopInfoRequest {
+            window: TopInfoWindow::Custom,
+            start: Some(0),
+            end: Some(200_000),
+            limit: None,
+            group_by: Some(TopInfoGroupBy::Source),
+        });
+        assert!(got.is_ok());
+        let got = got.unwrap();
+        let got_url = got
+            .top_article_infos
+            .into_iter()
+            .map(|info| info.url)
+            .collect::<Vec<_>>();
         let expect_url = vec!["url2".to_string(), "url1".to_string(), "url3".to_string()];
         assert_eq!(got_url, expect_url);
+        let got_groups = got.groups.unwrap();
+        assert_eq!(got_groups.len(), 3);
+
+        let got = udiab_model.get_top_info(TopInfoRequest {
+            window: TopInfoWindow::Custom,
+            start: None,
+            end: None,
+            limit: None,
+            group_by: None,
+        });
+        assert!(matches!(got, Err(UserError::BadRequest(_))));
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_cached() {
+    create_data(|udiab_model| {
+        for key in ["标题", " 标题  "] {
+            let got = udiab_model.get_retrieved_info(
+                key.to_string(),
+                AdvanceSearchOptions {
+                    search_field: SearchField::All,
+                    sort_by: SearchSortBy::Relevance,
+                    search_method: SearchMethod::Naive,
+                },
+                0,
+                10,
+            );
+            assert!(got.is_ok());
+        }
+        let stats = udiab_model.cache_stats().retrieved_info;
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_after_cursor() {
+    create_data(|udiab_model| {
+        for (sort_by, expect_url) in [
+            (SearchSortBy::Relevance, vec!["url3", "url1", "url2"]),
+            (SearchSortBy::Time, vec!["url1", "url2", "url3"]),
+            (SearchSortBy::Hot, vec!["url2", "url1", "url3"]),
+        ] {
+            let advanced_search_options = AdvanceSearchOptions {
+                search_field: SearchField::All,
+                sort_by,
+                search_method: SearchMethod::Naive,
+            };
+            let mut got = udiab_model
+                .get_retrieved_info("标题".to_string(), advanced_search_options, 0, 1)
+                .unwrap();
+            let mut got_url = vec![];
+            loop {
+                got_url.extend(got.article_infos.into_iter().map(|info| info.url));
+                match got.next_cursor {
+                    Some(cursor) => {
+                        got = udiab_model
+                            .get_retrieved_info_after(
+                                "标题".to_string(),
+                                advanced_search_options,
+                                &cursor,
+                                1,
+                            )
+                            .unwrap();
+                    }
+                    None => break,
+                }
+            }
+            assert_eq!(got_url, expect_url);
+        }
+
+        let got = udiab_model.get_retrieved_info_after(
+            "标题".to_string(),
+            AdvanceSearchOptions {
+                search_field: SearchField::All,
+                sort_by: SearchSortBy::Time,
+                search_method: SearchMethod::Naive,
+            },
+            "not a cursor",
+            1,
+        );
+        assert!(matches!(got, Err(UserError::BadRequest(_))));
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_after_cursor_across_write_back() {
+    create_data(|udiab_model| {
+        for (sort_by, valid) in [
+            (SearchSortBy::Relevance, true),
+            (SearchSortBy::Time, true),
+            (SearchSortBy::Hot, false),
+        ] {
+            let advanced_search_options = AdvanceSearchOptions {
+                search_field: SearchField::All,
+                sort_by,
+                search_method: SearchMethod::Naive,
+            };
+            let cursor = udiab_model
+                .get_retrieved_info("标题".to_string(), advanced_search_options, 0, 1)
+                .unwrap()
+                .next_cursor
+                .unwrap();
+            udiab_model.popularity.record_click("url1");
+            udiab_model
+                .popularity
+                .write_back(
+                    &udiab_model.reader.searcher(),
+                    udiab_model.project_document.url,
+                )
+                .unwrap();
+            let got = udiab_model.get_retrieved_info_after(
+                "标题".to_string(),
+                advanced_search_options,
+                &cursor,
+                1,
+            );
+            if valid {
+                assert_eq!(got.unwrap().article_infos.len(), 1);
+            } else {
+                assert!(matches!(got, Err(UserError::Conflict(_))));
+            }
+        }
+    });
+}
+
+#[test]
+fn test_search_with_bool_clauses() {
+    create_data(|udiab_model| {
+        let search_request: SearchRequest = serde_json::from_value(serde_json::json!({
+            "query": {
+                "bool": {
+                    "must": [{ "match": { "key": "标题" } }],
+                    "mustNot": [{ "match": { "key": "知道", "field": "body" } }]
+                }
+            },
+            "sort": [{ "by": "likes", "order": "asc" }]
+        }))
+        .unwrap();
+        let got = udiab_model.search(&search_request).unwrap();
+        let got_url = got
+            .article_infos
+            .into_iter()
+            .map(|info| info.url)
+            .collect::<Vec<_>>();
+        assert_eq!(got_url, vec!["url1", "url2"]);
+    });
+}
+
+#[test]
+fn test_search_with_filters_and_cursor() {
+    create_data(|udiab_model| {
+        let mut search_request: SearchRequest = serde_json::from_value(serde_json::json!({
+            "query": {
+                "bool": {
+                    "should": [
+                        { "match": { "key": "标题", "field": "title" } },
+                        { "match": { "key": "包含", "field": "title" } }
+                    ]
+                }
+            },
+            "filters": [{ "time": { "gte": 1000, "lt": 200000 } }],
+            "sort": [{ "by": "time" }],
+            "pagination": { "pageSize": 1 }
+        }))
+        .unwrap();
+        let mut got_url = vec![];
+        loop {
+            let got = udiab_model.search(&search_request).unwrap();
+            got_url.extend(got.article_infos.into_iter().map(|info| info.url));
+            match got.next_cursor {
+                Some(cursor) => search_request.pagination.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(got_url, vec!["url1", "url2"]);
+
+        search_request.sort = vec![SortSpec::from(SearchSortBy::Hot)];
+        let got = udiab_model.search(&search_request);
+        assert!(matches!(got, Err(UserError::BadRequest(_))));
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_v2_same_as_v1() {
+    use actix_web::web::Query;
+
+    create_data(|udiab_model| {
+        let Query(v1_request) = Query::<RetrievedInfoRequest>::from_query(
+            "key=标题&sortBy=1&searchField=2&searchMethod=0&pageSize=10",
+        )
+        .unwrap();
+        let Query(v2_request) = Query::<v2::RetrievedInfoRequest>::from_query(
+            "key=标题&sortBy=hot&searchField=all&searchMethod=naive&pageSize=10",
+        )
+        .unwrap();
+        let v2_request: RetrievedInfoRequest = v2_request.into();
+        assert!(v1_request.advanced_search_options == v2_request.advanced_search_options);
+
+        let got = udiab_model
+            .get_retrieved_info(
+                v2_request.key,
+                v2_request.advanced_search_options,
+                v2_request.offset,
+                v2_request.page_size,
+            )
+            .unwrap();
+        let got_url = got
+            .article_infos
+            .into_iter()
+            .map(|info| info.url)
+            .collect::<Vec<_>>();
+        assert_eq!(got_url, vec!["url2", "url1", "url3"]);
+
+        assert!(
+            Query::<v2::TopInfoRequest>::from_query("window=week&groupBy=codeLanguage").is_ok()
+        );
+        assert!(Query::<v2::TopInfoRequest>::from_query("window=1").is_err());
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_by_regex_partial() {
+    create_data(|udiab_model| {
+        let advanced_search_options = AdvanceSearchOptions {
+            search_field: SearchField::Title,
+            sort_by: SearchSortBy::Time,
+            search_method: SearchMethod::Regex,
+        };
+        let got = udiab_model
+            .get_retrieved_info("标.*".to_string(), advanced_search_options, 0, 10)
+            .unwrap();
+        assert!(!got.partial);
+        let got_url = got
+            .article_infos
+            .into_iter()
+            .map(|info| info.url)
+            .collect::<Vec<_>>();
+        assert_eq!(got_url, vec!["url1", "url2", "url3"]);
+
+        udiab_model.set_limits(LimitsConfig {
+            max_regex_expanded_terms: 0,
+            ..LimitsConfig::default()
+        });
+        let got = udiab_model
+            .get_retrieved_info("这.*".to_string(), advanced_search_options, 0, 10)
+            .unwrap();
+        assert!(got.partial);
+        assert!(got.article_infos.is_empty());
+
+        udiab_model.set_limits(LimitsConfig {
+            regex_time_budget: 0,
+            ..LimitsConfig::default()
+        });
+        let got = udiab_model
+            .get_retrieved_info("标题.*".to_string(), advanced_search_options, 0, 10)
+            .unwrap();
+        assert!(got.partial);
+        assert!(got.article_infos.is_empty());
+
+        udiab_model.set_limits(LimitsConfig::default());
+        assert!(matches!(
+            udiab_model.get_retrieved_info(
+                "标\\w{500}".to_string(),
+                advanced_search_options,
+                0,
+                10
+            ),
+            Err(UserError::BadRequest(_))
+        ));
+
+        udiab_model.set_limits(LimitsConfig {
+            max_regex_automaton_size: 0,
+            ..LimitsConfig::default()
+        });
+        assert!(matches!(
+            udiab_model.get_retrieved_info("标题.+".to_string(), advanced_search_options, 0, 10),
+            Err(UserError::BadRequest(_))
+        ));
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_after_deadline() {
+    use crate::deadline::Deadline;
+    use std::time::Duration;
+
+    create_data(|udiab_model| {
+        let advanced_search_options = AdvanceSearchOptions {
+            search_field: SearchField::All,
+            sort_by: SearchSortBy::Relevance,
+            search_method: SearchMethod::Naive,
+        };
+        let got = Deadline::new(Duration::from_secs(0))
+            .scope(|| {
+                udiab_model.get_retrieved_info("标题".to_string(), advanced_search_options, 0, 10)
+            })
+            .unwrap();
+        assert!(got.partial);
+
+        let got = udiab_model
+            .get_retrieved_info("标题".to_string(), advanced_search_options, 0, 10)
+            .unwrap();
+        assert!(!got.partial);
+        assert_eq!(got.article_infos.len(), 3);
+        let stats = udiab_model.cache_stats().retrieved_info;
+        assert_eq!(stats.hits, 0);
+    });
+}
+
+#[test]
+fn test_export() {
+    use futures::{channel::mpsc, executor::block_on, StreamExt};
+    use std::sync::atomic::AtomicBool;
+    use std::thread;
+
+    create_data(|udiab_model| {
+        let export_request: ExportRequest = serde_json::from_value(serde_json::json!({
+            "key": "标题",
+            "field": "title",
+            "start": 1000,
+            "includeBody": true
+        }))
+        .unwrap();
+        let prepared_export = udiab_model.prepare_export(&export_request).unwrap();
+        assert!(!prepared_export.partial);
+        // Smaller buffer than matches, so that export waits for reading
+        let (sender, receiver) = mpsc::channel(0);
+        let exporting =
+            thread::spawn(move || prepared_export.run(sender, Arc::new(AtomicBool::new(false))));
+        let got = block_on(receiver.collect::<Vec<_>>())
+            .into_iter()
+            .map(|exported_article| exported_article.unwrap())
+            .collect::<Vec<_>>();
+        exporting.join().unwrap();
+        let got_url = got
+            .iter()
+            .map(|exported_article| exported_article.url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(got_url, vec!["url1", "url2"]);
+        assert_eq!(got[0].source, "url1");
+        assert_eq!(got[0].likes, 23);
+        assert!(got[0].body.is_some());
+
+        // Export stops once receiver is dropped
+        let export_request: ExportRequest = serde_json::from_value(serde_json::json!({})).unwrap();
+        let prepared_export = udiab_model.prepare_export(&export_request).unwrap();
+        let (sender, mut receiver) = mpsc::channel(0);
+        let exporting =
+            thread::spawn(move || prepared_export.run(sender, Arc::new(AtomicBool::new(false))));
+        assert!(block_on(receiver.next()).is_some());
+        drop(receiver);
+        exporting.join().unwrap();
+
+        // Cancelled export sends nothing
+        let prepared_export = udiab_model.prepare_export(&export_request).unwrap();
+        let (sender, receiver) = mpsc::channel(0);
+        prepared_export.run(sender, Arc::new(AtomicBool::new(true)));
+        assert!(block_on(receiver.collect::<Vec<_>>()).is_empty());
+    });
+}
+
+#[test]
+fn test_get_aggregations() {
+    create_data(|udiab_model| {
+        let aggregations_request: AggregationsRequest = serde_json::from_value(serde_json::json!({
+            "key": "标题",
+            "searchField": "0",
+            "searchMethod": "0",
+            "interval": "0",
+            "likesBoundaries": "10,30",
+            "topSources": 2
+        }))
+        .unwrap();
+        let got = udiab_model.get_aggregations(&aggregations_request).unwrap();
+        assert_eq!(got.total, 3);
+        assert!(!got.partial);
+        assert_eq!(
+            got.date_histogram,
+            vec![DateBucket {
+                start: Utc.timestamp(0, 0),
+                count: 3
+            }]
+        );
+        let got_likes_buckets = got
+            .likes_buckets
+            .iter()
+            .map(|bucket| (bucket.from, bucket.to, bucket.count))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            got_likes_buckets,
+            vec![(0, Some(10), 1), (10, Some(30), 1), (30, None, 1)]
+        );
+        let got_sources = got
+            .top_sources
+            .iter()
+            .map(|source_count| source_count.source.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(got_sources, vec!["url1", "url2"]);
+
+        // Pinyin matches the same titles as `/retrieved_info`
+        let pinyin_aggregations_request: AggregationsRequest =
+            serde_json::from_value(serde_json::json!({
+                "key": "biaoti",
+                "searchField": "0",
+                "searchMethod": "0"
+            }))
+            .unwrap();
+        let got = udiab_model
+            .get_aggregations(&pinyin_aggregations_request)
+            .unwrap();
+        assert_eq!(got.total, 3);
+
+        let aggregations_request = AggregationsRequest {
+            likes_boundaries: Some("30,10".to_string()),
+            ..aggregations_request
+        };
+        let got = udiab_model.get_aggregations(&aggregations_request);
+        assert!(matches!(got, Err(UserError::BadRequest(_))));
+    });
+}
+
+#[test]
+fn test_explain() {
+    create_data(|udiab_model| {
+        let explain_request: ExplainRequest = serde_json::from_value(serde_json::json!({
+            "key": "标题",
+            "searchField": "0",
+            "searchMethod": "0",
+            "url": "url1"
+        }))
+        .unwrap();
+        let got = udiab_model.explain(&explain_request).unwrap();
+        assert!(got.matched);
+        assert!(got.explanation.unwrap().value > 0.0);
+        assert_eq!(got.field_tokens.len(), 1);
+        assert_eq!(got.field_tokens[0].field, "title");
+        assert!(!got.field_tokens[0].tokens.is_empty());
+        assert!(got.field_tokens[0]
+            .tokens
+            .iter()
+            .all(|explained_token| explained_token.matched));
+
+        let explain_request = ExplainRequest {
+            url: "url4".to_string(),
+            ..explain_request
+        };
+        let got = udiab_model.explain(&explain_request).unwrap();
+        assert!(!got.matched);
+        assert!(got.explanation.is_none());
+        assert!(got.field_tokens[0]
+            .tokens
+            .iter()
+            .all(|explained_token| !explained_token.matched));
+
+        let explain_request = ExplainRequest {
+            url: "url5".to_string(),
+            ..explain_request
+        };
+        let got = udiab_model.explain(&explain_request);
+        assert!(matches!(got, Err(UserError::NotFound(_))));
+    });
+}
+
+#[test]
+fn test_get_analyses() {
+    create_data(|udiab_model| {
+        let got = udiab_model.get_analyses("rust异步").unwrap();
+        let got_tokenizers = got
+            .iter()
+            .map(|analysis| analysis.tokenizer.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(got_tokenizers, search_base::tokenizer_names());
+
+        let code_analysis = got
+            .iter()
+            .find(|analysis| analysis.tokenizer == search_base::CODE_TOKENIZER)
+            .unwrap();
+        assert_eq!(
+            code_analysis.tokens,
+            vec![AnalyzedToken {
+                text: "rust异步".to_string(),
+                offset_from: 0,
+                offset_to: 10,
+                position: 0,
+            }]
+        );
+        let default_analysis = got
+            .iter()
+            .find(|analysis| analysis.tokenizer == "CANG_JIE_DEFAULT")
+            .unwrap();
+        let got_texts = default_analysis
+            .tokens
+            .iter()
+            .map(|token| token.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(got_texts, vec!["rust", "异步"]);
+    });
+}
+
+/// Replace synonyms of `udiab_model` by dictionary at `path`
+fn with_synonyms(udiab_model: UdiabModel, path: &std::path::Path) -> UdiabModel {
+    UdiabModel {
+        synonyms: Arc::new(Synonyms::open(Some(path.to_path_buf()), 0.5).unwrap()),
+        ..udiab_model
+    }
+}
+
+#[test]
+fn test_get_retrieved_info_with_synonyms() {
+    create_data(|udiab_model| {
+        let search = |udiab_model: &UdiabModel, search_method: SearchMethod| {
+            udiab_model
+                .get_retrieved_info(
+                    "heading".to_string(),
+                    AdvanceSearchOptions {
+                        search_field: SearchField::Title,
+                        sort_by: SearchSortBy::Time,
+                        search_method,
+                    },
+                    0,
+                    10,
+                )
+                .unwrap()
+                .article_infos
+                .into_iter()
+                .map(|info| info.url)
+                .collect::<Vec<_>>()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("synonyms.txt");
+        fs::write(&path, "# title\nheading, 标题, 题\n").unwrap();
+        let udiab_model = with_synonyms(udiab_model, &path);
+        for search_method in [
+            SearchMethod::Naive,
+            SearchMethod::Complex,
+            SearchMethod::Regex,
+        ] {
+            assert_eq!(
+                search(&udiab_model, search_method),
+                vec!["url1", "url2", "url3"]
+            );
+        }
+    });
+}
+
+#[test]
+fn test_reload_synonyms() {
+    create_data(|udiab_model| {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("synonyms.txt");
+        fs::write(&path, "k8s, 标题\n").unwrap();
+        let udiab_model = with_synonyms(udiab_model, &path);
+        let search = || {
+            udiab_model
+                .get_retrieved_info(
+                    "k8s".to_string(),
+                    AdvanceSearchOptions {
+                        search_field: SearchField::All,
+                        sort_by: SearchSortBy::Time,
+                        search_method: SearchMethod::Naive,
+                    },
+                    0,
+                    10,
+                )
+                .unwrap()
+                .article_infos
+                .len()
+        };
+        assert_eq!(search(), 3);
+
+        fs::write(&path, "k8s, kubernetes\n").unwrap();
+        assert_eq!(udiab_model.synonyms.reload().unwrap(), 2);
+        // Cached result is dropped
+        assert_eq!(search(), 0);
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_by_traditional_key() {
+    create_data(|udiab_model| {
+        for key in ["標題", "这是一个标题", "這是一個標題"] {
+            let got = udiab_model
+                .get_retrieved_info(
+                    key.to_string(),
+                    AdvanceSearchOptions {
+                        search_field: SearchField::Title,
+                        sort_by: SearchSortBy::Time,
+                        search_method: SearchMethod::Naive,
+                    },
+                    0,
+                    10,
+                )
+                .unwrap();
+            let got_url = got
+                .article_infos
+                .iter()
+                .map(|info| info.url.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(got_url, vec!["url1", "url2", "url3"]);
+            // Highlighted in original title
+            let title_snippet = &got.article_infos[0].title_snippet;
+            assert_eq!(title_snippet.fragments, "这是一个标题");
+            assert!(!title_snippet.highlighted_positions.is_empty());
+        }
+    });
+}
+
+#[test]
+fn test_get_key_hints_by_pinyin() {
+    create_data(|udiab_model| {
+        for key in ["zheshi", "ZheShi", "zs", "zhe s"] {
+            let got = udiab_model.get_key_hints(key.to_string()).unwrap();
+            assert_eq!(got.len(), 3, "{}", key);
+            assert_eq!(got[0].fragments, "这是一个标题");
+            // `这是` is highlighted
+            assert_eq!(got[0].highlighted_positions, vec![0..6]);
+        }
+        assert!(udiab_model
+            .get_key_hints("shujuku".to_string())
+            .unwrap()
+            .is_empty());
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_by_pinyin() {
+    create_data(|udiab_model| {
+        let search = |key: &str, search_field: SearchField| {
+            udiab_model
+                .get_retrieved_info(
+                    key.to_string(),
+                    AdvanceSearchOptions {
+                        search_field,
+                        sort_by: SearchSortBy::Time,
+                        search_method: SearchMethod::Naive,
+                    },
+                    0,
+                    10,
+                )
+                .unwrap()
+                .article_infos
+                .into_iter()
+                .map(|info| info.url)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            search("biaoti", SearchField::Title),
+            vec!["url1", "url2", "url3"]
+        );
+        assert_eq!(search("bubaohan", SearchField::Title), vec!["url4"]);
+        // Only full syllables fall back, unlike key hints
+        assert!(search("bt", SearchField::Title).is_empty());
+        // Fallback is only for titles
+        assert!(search("biaoti", SearchField::All).is_empty());
+    });
+}
+
+#[test]
+fn test_get_retrieved_info_by_mixed_script_key() {
+    create_data_from("test_data/mixed_data.json", |udiab_model| {
+        let search = |key: &str| {
+            udiab_model
+                .get_retrieved_info(
+                    key.to_string(),
+                    AdvanceSearchOptions {
+                        search_field: SearchField::Title,
+                        sort_by: SearchSortBy::Time,
+                        search_method: SearchMethod::Naive,
+                    },
+                    0,
+                    10,
+                )
+                .unwrap()
+                .article_infos
+                .into_iter()
+                .map(|info| info.url)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(search("springboot"), vec!["url1"]);
+        assert_eq!(search("SpringBoot 2.X"), vec!["url1"]);
+        assert_eq!(search("redis整合"), Vec::<String>::new());
+        assert_eq!(search("整合redis"), vec!["url1"]);
+        // Version is a token of its own, not a prefix of another one
+        assert_eq!(search("python3"), vec!["url2"]);
+        assert_eq!(search("Python 3.10"), vec!["url3"]);
+        assert_eq!(search("ASYNC"), vec!["url3"]);
+        assert_eq!(search("c++"), vec!["url2"]);
+    });
+}
+
+#[test]
+fn test_malformed_complex_key() {
+    create_data(|udiab_model| {
+        let got = udiab_model.get_retrieved_info(
+            "title:(标题".to_string(),
+            AdvanceSearchOptions {
+                search_field: SearchField::All,
+                sort_by: SearchSortBy::Relevance,
+                search_method: SearchMethod::Complex,
+            },
+            0,
+            10,
+        );
+        assert!(matches!(got, Err(UserError::BadRequest(_))));
+        let got = udiab_model.get_key_hints("nosuchfield:标题".to_string());
+        assert!(matches!(got, Err(UserError::BadRequest(_))));
+    });
+}
+
+#[test]
+fn test_get_more_like_this_out_of_range() {
+    create_data(|udiab_model| {
+        for (segment_ord, doc_id) in [(0, 1000), (1000, 0)] {
+            let got = udiab_model.get_more_like_this(
+                UdiabDocAddress {
+                    segment_ord,
+                    doc_id,
+                },
+                0,
+                10,
+            );
+            assert!(matches!(got, Err(UserError::BadRequest(_))));
+        }
+        assert!(udiab_model
+            .get_more_like_this(
+                UdiabDocAddress {
+                    segment_ord: 0,
+                    doc_id: 0,
+                },
+                0,
+                10,
+            )
+            .is_ok());
     });
 }
@@ -0,0 +1,120 @@
+use super::auth::API_KEY_HEADER;
+use super::interfaces::*;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    Modify, OpenApi,
+};
+
+/// OpenAPI document of all endpoints, served at `/api/openapi.json`.
+///
+/// Endpoints under `/api` are also served under `/api/v2`, and only those
+/// whose requests contain enums are documented twice.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "UDIAB",
+        description = "Search engine for programming articles"
+    ),
+    paths(
+        crate::get_key_hints,
+        crate::get_retrieved_info,
+        crate::get_retrieved_info_v2,
+        crate::post_search,
+        crate::get_export,
+        crate::get_aggregations,
+        crate::get_aggregations_v2,
+        crate::get_explain,
+        crate::get_explain_v2,
+        crate::get_analyze,
+        crate::get_top_info,
+        crate::get_top_info_v2,
+        crate::get_more_like_this,
+        crate::post_click,
+        crate::get_cache_stats,
+        crate::post_reload,
+        crate::post_reload_synonyms,
+    ),
+    components(schemas(
+        Snippet,
+        HighlightedRange,
+        UdiabDocAddress,
+        SearchedArticleInfo,
+        RetrievedInfo,
+        AdvanceSearchOptions,
+        SearchSortBy,
+        SearchField,
+        SearchMethod,
+        SortSpec,
+        SortField,
+        SortOrder,
+        TopInfoWindow,
+        TopInfoGroupBy,
+        TopArticleInfo,
+        TopArticleGroup,
+        TopInfo,
+        MoreLikeThisArticleInfo,
+        HistogramInterval,
+        DateBucket,
+        LikesBucket,
+        SourceCount,
+        Aggregations,
+        AnalyzedToken,
+        Analysis,
+        ExplanationNode,
+        FieldTokens,
+        ExplainedToken,
+        Explain,
+        CacheStats,
+        ClickRequest,
+        SearchRequest,
+        QueryClause,
+        MatchClause,
+        BoolClause,
+        MatchField,
+        MatchMethod,
+        SearchFilter,
+        Pagination,
+        ExportFormat,
+        ExportedArticle,
+        KeyHintsResponse,
+        RetrievedInfoResponse,
+        TopArticleInfoResponse,
+        AggregationsResponse,
+        ExplainResponse,
+        AnalyzeResponse,
+        MoreLikeThisResponse,
+        CacheStatsResponse,
+        v2::AdvanceSearchOptions,
+        v2::SearchSortBy,
+        v2::SearchField,
+        v2::SearchMethod,
+        v2::TopInfoWindow,
+        v2::TopInfoGroupBy,
+        v2::HistogramInterval,
+    )),
+    modifiers(&ApiKeySecurity)
+)]
+pub struct ApiDoc;
+
+/// Adds the API key scheme required by `/api/admin` endpoints
+struct ApiKeySecurity;
+
+impl Modify for ApiKeySecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new(API_KEY_HEADER))),
+            );
+        }
+    }
+}
+
+/// Pretty-printed OpenAPI document
+pub fn openapi_json() -> String {
+    ApiDoc::openapi().to_pretty_json().unwrap_or_default()
+}
+
+#[cfg(test)]
+#[path = "./openapi_test.rs"]
+mod openapi_test;
@@ -0,0 +1,20 @@
+use super::*;
+use std::env;
+use std::fs;
+
+/// Checked-in copy of OpenAPI document, which the frontend is built against
+const OPENAPI_PATH: &str = "openapi.json";
+
+#[test]
+fn test_openapi_up_to_date() {
+    let generated = openapi_json();
+    if env::var_os("UPDATE_OPENAPI").is_some() {
+        fs::write(OPENAPI_PATH, &generated).unwrap();
+    }
+    let checked_in = fs::read_to_string(OPENAPI_PATH).unwrap_or_default();
+    assert!(
+        generated == checked_in,
+        "OpenAPI document has changed, run `UPDATE_OPENAPI=1 cargo test` in backend and commit {}",
+        OPENAPI_PATH
+    );
+}
@@ -0,0 +1,233 @@
+use super::interfaces::UserError;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tantivy::{
+    collector::{CustomScorer, CustomSegmentScorer},
+    fastfield::{DynamicFastFieldReader, FastFieldReader},
+    schema::{Field, IndexRecordOption},
+    DocId, DocSet, Searcher, SegmentId, SegmentReader, Term, TERMINATED,
+};
+
+/// Side-car fast field store of click counts, keyed by doc id in each segment
+#[derive(Default, PartialEq)]
+pub struct PopularitySnapshot {
+    segments: HashMap<SegmentId, HashMap<DocId, u64>>,
+}
+
+impl PopularitySnapshot {
+    /// Click count of doc in given segment
+    ///
+    /// Returns 0 if the doc has never been clicked, or if the segment is
+    /// created after the last write back.
+    pub fn clicks(&self, segment_id: SegmentId, doc_id: DocId) -> u64 {
+        self.segments
+            .get(&segment_id)
+            .and_then(|docs| docs.get(&doc_id))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+/// Internal popularity counter of articles, fed by click events
+///
+/// Counters are keyed by url (the stable identity of an article), and are
+/// periodically written back into a [`PopularitySnapshot`] keyed by doc id,
+/// so that they can be read as cheap as a fast field during collecting.
+pub struct Popularity {
+    /// Click counts keyed by url
+    counts: Mutex<HashMap<String, u64>>,
+    /// Snapshot built at last write back
+    snapshot: RwLock<Arc<PopularitySnapshot>>,
+    /// Count of write backs changing snapshot, i.e., version of snapshot
+    version: AtomicU64,
+    /// Click counts last persisted, so that unchanged counts are not
+    /// written again
+    persisted_counts: Mutex<HashMap<String, u64>>,
+    /// Weight of one click compared to one scraped like
+    click_weight: AtomicU64,
+    /// Where counts are persisted. If `None`, counts are kept in memory only
+    store_path: Option<PathBuf>,
+}
+
+impl Popularity {
+    /// Create popularity counter, loading persisted counts from `store_path`
+    /// if it exists.
+    pub fn open(store_path: Option<PathBuf>, click_weight: u64) -> Result<Self, UserError> {
+        let counts: HashMap<String, u64> = match &store_path {
+            Some(store_path) if store_path.exists() => {
+                let counts_str = fs::read_to_string(store_path).map_err(|error| {
+                    UserError::Unexpected(format!("Read popularity store failed: {}", error))
+                })?;
+                serde_json::from_str(&counts_str).map_err(|error| {
+                    UserError::Unexpected(format!("Parse popularity store failed: {}", error))
+                })?
+            }
+            _ => HashMap::new(),
+        };
+        Ok(Self {
+            persisted_counts: Mutex::new(counts.clone()),
+            counts: Mutex::new(counts),
+            snapshot: RwLock::new(Arc::new(PopularitySnapshot::default())),
+            version: AtomicU64::new(0),
+            click_weight: AtomicU64::new(click_weight),
+            store_path,
+        })
+    }
+
+    /// Record one click on article with given url
+    pub fn record_click(&self, url: &str) {
+        if let Ok(mut counts) = self.counts.lock() {
+            *counts.entry(url.to_string()).or_insert(0) += 1;
+        }
+    }
+
+    /// Current snapshot for hot scoring
+    pub fn snapshot(&self) -> Arc<PopularitySnapshot> {
+        match self.snapshot.read() {
+            Ok(snapshot) => snapshot.clone(),
+            Err(_) => Arc::new(PopularitySnapshot::default()),
+        }
+    }
+
+    /// Version of current snapshot, which changes after every write back
+    /// changing it
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::Relaxed)
+    }
+
+    /// Scorer computing hot score of each doc, which is scraped likes
+    /// blended with click count.
+    ///
+    /// Used with [`tantivy::collector::TopDocs::custom_score`].
+    pub fn hot_scorer(&self, likes_field: Field) -> HotScorer {
+        HotScorer {
+            snapshot: self.snapshot(),
+            click_weight: self.click_weight.load(Ordering::Relaxed),
+            likes_field,
+        }
+    }
+
+    /// Change weight of one click, which bumps version since hot scores change
+    pub fn set_click_weight(&self, click_weight: u64) {
+        if self.click_weight.swap(click_weight, Ordering::Relaxed) != click_weight {
+            self.version.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Resolve counters to doc ids in current searcher, and persist counters
+    ///
+    /// Version is bumped only if the snapshot changes, i.e., clicks are
+    /// recorded or index is reloaded since last write back, so that caches
+    /// keyed by version survive idle intervals.
+    pub fn write_back(&self, searcher: &Searcher, url_field: Field) -> Result<(), UserError> {
+        let counts = self
+            .counts
+            .lock()
+            .map_err(|_| UserError::Unexpected("Popularity counter is poisoned".to_string()))?
+            .clone();
+
+        let mut segments = HashMap::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader
+                .inverted_index(url_field)
+                .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
+            let mut docs = HashMap::new();
+            for (url, clicks) in &counts {
+                let term = Term::from_field_text(url_field, url);
+                let postings = inverted_index
+                    .read_postings(&term, IndexRecordOption::Basic)
+                    .map_err(|error| {
+                        UserError::Unexpected(format!("Read url postings failed: {}", error))
+                    })?;
+                if let Some(mut postings) = postings {
+                    let mut doc_id = postings.doc();
+                    while doc_id != TERMINATED {
+                        if !segment_reader.is_deleted(doc_id) {
+                            docs.insert(doc_id, *clicks);
+                        }
+                        doc_id = postings.advance();
+                    }
+                }
+            }
+            if !docs.is_empty() {
+                segments.insert(segment_reader.segment_id(), docs);
+            }
+        }
+        let new_snapshot = PopularitySnapshot { segments };
+        if let Ok(mut snapshot) = self.snapshot.write() {
+            if **snapshot != new_snapshot {
+                *snapshot = Arc::new(new_snapshot);
+                self.version.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        if let Some(store_path) = &self.store_path {
+            let mut persisted_counts = self
+                .persisted_counts
+                .lock()
+                .map_err(|_| UserError::Unexpected("Popularity counter is poisoned".to_string()))?;
+            if *persisted_counts != counts {
+                let counts_str = serde_json::to_string(&counts).map_err(|error| {
+                    UserError::Unexpected(format!("Serialize popularity store failed: {}", error))
+                })?;
+                write_atomically(store_path, &counts_str).map_err(|error| {
+                    UserError::Unexpected(format!("Write popularity store failed: {}", error))
+                })?;
+                *persisted_counts = counts;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Write `content` to a temporary file next to `path` and rename it to
+/// `path`, so that a crash while writing never leaves a truncated file
+fn write_atomically(path: &Path, content: &str) -> std::io::Result<()> {
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    let temporary_path = PathBuf::from(temporary_path);
+    let mut file = File::create(&temporary_path)?;
+    file.write_all(content.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temporary_path, path)
+}
+
+/// Custom scorer for hot sorting, see [`Popularity::hot_scorer`]
+pub struct HotScorer {
+    snapshot: Arc<PopularitySnapshot>,
+    click_weight: u64,
+    likes_field: Field,
+}
+
+impl CustomScorer<u64> for HotScorer {
+    type Child = HotSegmentScorer;
+
+    fn segment_scorer(&self, segment_reader: &SegmentReader) -> tantivy::Result<Self::Child> {
+        Ok(HotSegmentScorer {
+            snapshot: self.snapshot.clone(),
+            click_weight: self.click_weight,
+            segment_id: segment_reader.segment_id(),
+            likes_reader: segment_reader.fast_fields().u64(self.likes_field)?,
+        })
+    }
+}
+
+/// Per-segment child of [`HotScorer`]
+pub struct HotSegmentScorer {
+    snapshot: Arc<PopularitySnapshot>,
+    click_weight: u64,
+    segment_id: SegmentId,
+    likes_reader: DynamicFastFieldReader<u64>,
+}
+
+impl CustomSegmentScorer<u64> for HotSegmentScorer {
+    fn score(&mut self, doc_id: DocId) -> u64 {
+        let likes = self.likes_reader.get(doc_id);
+        let clicks = self.snapshot.clicks(self.segment_id, doc_id);
+        likes.saturating_add(clicks.saturating_mul(self.click_weight))
+    }
+}
@@ -0,0 +1,214 @@
+use super::auth::API_KEY_HEADER;
+use super::config::{AuthConfig, RateLimitConfig};
+use super::interfaces::UserError;
+use actix_web::dev::ServiceRequest;
+use lru::LruCache;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+/// Tokens of a client, refilled continuously and taken one per request
+struct TokenBucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+/// Per-client token bucket rate limiter, together with global caps of
+/// concurrent expensive queries and exports
+///
+/// A client is identified by its IP. If it sends a configured API key, the
+/// key has a bucket of its own as well, and both buckets must have a token
+/// left. Unknown keys are ignored, so that they cannot fill the buckets.
+/// Clicks are limited by buckets of their own, see
+/// [`RateLimiter::check_click_request`].
+pub struct RateLimiter {
+    buckets: Mutex<LruCache<String, TokenBucket>>,
+    config: RwLock<RateLimitConfig>,
+    api_keys: HashSet<String>,
+    expensive_queries: Arc<AtomicUsize>,
+    exports: Arc<AtomicUsize>,
+}
+
+/// Slot of a running expensive query or export, released when dropped
+pub struct ConcurrencyPermit {
+    running: Arc<AtomicUsize>,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        self.running.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Occupy a slot of `running` if fewer than `max` are running, or if `max`
+/// is 0
+fn acquire(running: &Arc<AtomicUsize>, max: usize) -> Result<ConcurrencyPermit, UserError> {
+    running
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+            if max == 0 || count < max {
+                Some(count + 1)
+            } else {
+                None
+            }
+        })
+        .map_err(|_| UserError::TooManyRequests { retry_after: 1 })?;
+    Ok(ConcurrencyPermit {
+        running: running.clone(),
+    })
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, auth_config: &AuthConfig) -> Self {
+        Self {
+            buckets: Mutex::new(LruCache::new(config.max_clients.max(1))),
+            config: RwLock::new(config),
+            api_keys: auth_config
+                .api_keys
+                .iter()
+                .map(|api_key| api_key.key.clone())
+                .collect(),
+            expensive_queries: Arc::new(AtomicUsize::new(0)),
+            exports: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Apply new config. Remembered clients keep their tokens
+    pub fn reconfigure(&self, config: RateLimitConfig) {
+        if let Ok(mut buckets) = self.buckets.lock() {
+            buckets.resize(config.max_clients.max(1));
+        }
+        if let Ok(mut current_config) = self.config.write() {
+            *current_config = config;
+        }
+    }
+
+    fn config(&self) -> RateLimitConfig {
+        self.config
+            .read()
+            .map_or_else(|_| RateLimitConfig::default(), |config| config.clone())
+    }
+
+    /// Take a token of the client sending `request`
+    pub fn check_request(&self, request: &ServiceRequest) -> Result<(), UserError> {
+        self.check(&self.clients(request, ""), Instant::now())
+    }
+
+    /// Take a click token of the client sending `request`, so that clicks
+    /// neither eat into searches nor are allowed as many
+    pub fn check_click_request(&self, request: &ServiceRequest) -> Result<(), UserError> {
+        self.check_clicks(&self.clients(request, "click:"), Instant::now())
+    }
+
+    /// Names of buckets of the client sending `request`, prefixed by
+    /// `prefix`
+    fn clients(&self, request: &ServiceRequest, prefix: &str) -> Vec<String> {
+        let connection_info = request.connection_info();
+        let ip = if self.config().trust_forwarded_for {
+            connection_info.realip_remote_addr().map(str::to_string)
+        } else {
+            request.peer_addr().map(|address| address.ip().to_string())
+        };
+        let api_key = request
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|api_key| api_key.to_str().ok())
+            .filter(|api_key| self.api_keys.contains(*api_key));
+        let mut clients = vec![format!("{}ip:{}", prefix, ip.unwrap_or_default())];
+        if let Some(api_key) = api_key {
+            clients.push(format!("{}key:{}", prefix, api_key));
+        }
+        clients
+    }
+
+    /// Take a token from each bucket of `clients` at `now`
+    ///
+    /// No token is taken unless all buckets have one.
+    fn check(&self, clients: &[String], now: Instant) -> Result<(), UserError> {
+        let config = self.config();
+        self.take(clients, now, config.requests_per_second, config.burst)
+    }
+
+    /// Like [`RateLimiter::check`], with rate and burst of clicks
+    fn check_clicks(&self, clients: &[String], now: Instant) -> Result<(), UserError> {
+        let config = self.config();
+        self.take(clients, now, config.clicks_per_second, config.click_burst)
+    }
+
+    fn take(
+        &self,
+        clients: &[String],
+        now: Instant,
+        requests_per_second: f64,
+        burst: f64,
+    ) -> Result<(), UserError> {
+        if requests_per_second <= 0.0 {
+            return Ok(());
+        }
+        let mut buckets = self
+            .buckets
+            .lock()
+            .map_err(|_| UserError::Unexpected("Rate limiter is poisoned".to_string()))?;
+        let mut retry_after = 0.0_f64;
+        for client in clients {
+            if !buckets.contains(client) {
+                buckets.put(
+                    client.clone(),
+                    TokenBucket {
+                        tokens: burst,
+                        updated: now,
+                    },
+                );
+            }
+            let bucket = buckets.get_mut(client).unwrap();
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * requests_per_second).min(burst);
+            bucket.updated = now;
+            if bucket.tokens < 1.0 {
+                retry_after = retry_after.max((1.0 - bucket.tokens) / requests_per_second);
+            }
+        }
+        if retry_after > 0.0 {
+            return Err(UserError::TooManyRequests {
+                retry_after: retry_after.ceil() as u64,
+            });
+        }
+        for client in clients {
+            if let Some(bucket) = buckets.get_mut(client) {
+                bucket.tokens -= 1.0;
+            }
+        }
+        Ok(())
+    }
+
+    /// Occupy a slot of expensive queries, which should be held until the
+    /// query finishes
+    pub fn acquire_expensive(&self) -> Result<ConcurrencyPermit, UserError> {
+        acquire(
+            &self.expensive_queries,
+            self.config().max_concurrent_expensive_queries,
+        )
+    }
+
+    /// Occupy a slot of exports, which should be held until the export
+    /// thread finishes
+    pub fn acquire_export(&self) -> Result<ConcurrencyPermit, UserError> {
+        acquire(&self.exports, self.config().max_concurrent_exports)
+    }
+
+    /// Occupy a slot of expensive queries if `expensive`
+    pub fn acquire_expensive_if(
+        &self,
+        expensive: bool,
+    ) -> Result<Option<ConcurrencyPermit>, UserError> {
+        if expensive {
+            self.acquire_expensive().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./rate_limit_test.rs"]
+mod rate_limit_test;
@@ -0,0 +1,154 @@
+use super::*;
+use crate::auth::Role;
+use crate::config::ApiKeyConfig;
+use actix_web::{http::StatusCode, test::TestRequest, ResponseError};
+use std::time::Duration;
+
+fn create_rate_limiter(requests_per_second: f64, burst: f64) -> RateLimiter {
+    RateLimiter::new(
+        RateLimitConfig {
+            requests_per_second,
+            burst,
+            max_concurrent_expensive_queries: 1,
+            ..RateLimitConfig::default()
+        },
+        &AuthConfig {
+            api_keys: ["key", "another key"]
+                .iter()
+                .map(|key| ApiKeyConfig {
+                    key: key.to_string(),
+                    role: Role::Read,
+                })
+                .collect(),
+            ..AuthConfig::default()
+        },
+    )
+}
+
+#[test]
+fn test_check_refills_tokens() {
+    let rate_limiter = create_rate_limiter(1.0, 2.0);
+    let clients = ["ip:1.2.3.4".to_string()];
+    let now = Instant::now();
+    assert!(rate_limiter.check(&clients, now).is_ok());
+    assert!(rate_limiter.check(&clients, now).is_ok());
+    let got = rate_limiter.check(&clients, now);
+    assert!(matches!(
+        got,
+        Err(UserError::TooManyRequests { retry_after: 1 })
+    ));
+    assert!(rate_limiter.check(&["ip:5.6.7.8".to_string()], now).is_ok());
+    assert!(rate_limiter
+        .check(&clients, now + Duration::from_secs(1))
+        .is_ok());
+
+    let rate_limiter = create_rate_limiter(0.0, 0.0);
+    assert!(rate_limiter.check(&clients, now).is_ok());
+}
+
+#[test]
+fn test_check_request_with_api_key() {
+    let rate_limiter = create_rate_limiter(1.0, 1.0);
+    let request = |ip: &str, api_key: Option<&str>| {
+        let mut request = TestRequest::default().peer_addr(format!("{}:80", ip).parse().unwrap());
+        if let Some(api_key) = api_key {
+            request = request.header(API_KEY_HEADER, api_key);
+        }
+        request.to_srv_request()
+    };
+    assert!(rate_limiter
+        .check_request(&request("1.1.1.1", Some("key")))
+        .is_ok());
+    // Same key from another IP shares the bucket of key
+    assert!(rate_limiter
+        .check_request(&request("2.2.2.2", Some("key")))
+        .is_err());
+    // Bucket of IP is untouched by rejected request
+    assert!(rate_limiter
+        .check_request(&request("2.2.2.2", None))
+        .is_ok());
+    // Rotating keys does not bypass bucket of IP
+    assert!(rate_limiter
+        .check_request(&request("1.1.1.1", Some("another key")))
+        .is_err());
+    // Unknown key has no bucket of its own
+    assert!(rate_limiter
+        .check_request(&request("3.3.3.3", Some("unknown key")))
+        .is_ok());
+    assert!(!rate_limiter
+        .buckets
+        .lock()
+        .unwrap()
+        .contains(&"key:unknown key".to_string()));
+}
+
+#[test]
+fn test_check_click_request() {
+    let rate_limiter = RateLimiter::new(
+        RateLimitConfig {
+            requests_per_second: 1.0,
+            burst: 1.0,
+            clicks_per_second: 1.0,
+            click_burst: 2.0,
+            ..RateLimitConfig::default()
+        },
+        &AuthConfig::default(),
+    );
+    let request = || {
+        TestRequest::default()
+            .peer_addr("1.1.1.1:80".parse().unwrap())
+            .to_srv_request()
+    };
+    assert!(rate_limiter.check_request(&request()).is_ok());
+    // Clicks do not share bucket of searches
+    assert!(rate_limiter.check_click_request(&request()).is_ok());
+    assert!(rate_limiter.check_click_request(&request()).is_ok());
+    assert!(rate_limiter.check_click_request(&request()).is_err());
+    assert!(rate_limiter.check_request(&request()).is_err());
+}
+
+#[test]
+fn test_acquire_expensive() {
+    let rate_limiter = create_rate_limiter(1.0, 1.0);
+    let permit = rate_limiter.acquire_expensive().unwrap();
+    let got = rate_limiter.acquire_expensive();
+    assert!(matches!(got, Err(UserError::TooManyRequests { .. })));
+    assert!(rate_limiter.acquire_expensive_if(false).unwrap().is_none());
+    drop(permit);
+    assert!(rate_limiter.acquire_expensive_if(true).unwrap().is_some());
+}
+
+#[test]
+fn test_acquire_export() {
+    let rate_limiter = RateLimiter::new(
+        RateLimitConfig {
+            max_concurrent_exports: 1,
+            ..RateLimitConfig::default()
+        },
+        &AuthConfig::default(),
+    );
+    let permit = rate_limiter.acquire_export().unwrap();
+    assert!(matches!(
+        rate_limiter.acquire_export(),
+        Err(UserError::TooManyRequests { .. })
+    ));
+    // Exports are capped apart from expensive queries
+    assert!(rate_limiter.acquire_expensive().is_ok());
+    drop(permit);
+    assert!(rate_limiter.acquire_export().is_ok());
+}
+
+#[test]
+fn test_too_many_requests_response() {
+    let response = UserError::TooManyRequests { retry_after: 3 }.error_response();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(
+        response
+            .headers()
+            .get("Retry-After")
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        "3"
+    );
+}
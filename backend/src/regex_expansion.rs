@@ -0,0 +1,198 @@
+use super::deadline::Deadline;
+use super::interfaces::UserError;
+use regex_syntax::hir::literal::Literals;
+use std::cell::Cell;
+use std::collections::{BTreeSet, HashSet};
+use std::time::Instant;
+use tantivy::{
+    query::{BooleanQuery, Query, TermQuery},
+    schema::{Field, IndexRecordOption},
+    Searcher, Term,
+};
+use tantivy_fst::{Automaton, Regex};
+
+/// Longest literal prefix every match of regex starts with
+///
+/// Returns `None` if regex can not be parsed.
+pub fn regex_literal_prefix(pattern: &str) -> Option<String> {
+    let hir = regex_syntax::Parser::new().parse(pattern).ok()?;
+    let prefixes = Literals::prefixes(&hir);
+    let prefix = prefixes.longest_common_prefix();
+    // The common prefix of literals may end in the middle of a UTF-8 char
+    let valid_length = match std::str::from_utf8(prefix) {
+        Ok(_) => prefix.len(),
+        Err(error) => error.valid_up_to(),
+    };
+    Some(String::from_utf8_lossy(&prefix[..valid_length]).into_owned())
+}
+
+/// Least byte string greater than all strings starting with `prefix`
+///
+/// Returns `None` if there is no such string, i.e., `prefix` is empty or
+/// consists of `0xFF` only.
+fn prefix_successor(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut successor = prefix.to_vec();
+    while let Some(last) = successor.pop() {
+        if last < u8::MAX {
+            successor.push(last + 1);
+            return Some(successor);
+        }
+    }
+    None
+}
+
+/// Bytes taken by each state of the DFA tantivy builds for a regex, i.e., a
+/// transition table of 256 `Option<usize>`
+const REGEX_STATE_SIZE: usize = 256 * std::mem::size_of::<Option<usize>>();
+
+/// Build the automaton regex runs as on term dictionary, and reject it if it
+/// takes more than `max_automaton_size` bytes.
+///
+/// The size is measured on the DFA of `tantivy_fst::Regex` itself by
+/// walking all states reachable from the start state. This is done only
+/// here, inside the search pool, instead of in request validation, so that
+/// the automaton is built once and never on the async executor.
+fn build_regex(pattern: &str, max_automaton_size: usize) -> Result<Regex, UserError> {
+    let regex = Regex::new(pattern)
+        .map_err(|error| UserError::BadRequest(format!("Invalid regex: {}", error)))?;
+    let max_states = max_automaton_size / REGEX_STATE_SIZE;
+    let start = regex.start();
+    let mut visited = HashSet::from([start]);
+    let mut pending = vec![start];
+    while let Some(state) = pending.pop() {
+        for byte in 0..=u8::MAX {
+            let next = regex.accept(&state, byte);
+            if next.is_some() && visited.insert(next) {
+                if visited.len() > max_states {
+                    return Err(UserError::BadRequest(format!(
+                        "Regex is too large: automaton must not exceed {} bytes",
+                        max_automaton_size
+                    )));
+                }
+                pending.push(next);
+            }
+        }
+    }
+    Ok(regex)
+}
+
+/// Regex which stops matching once `expires_at` is reached or deadline of
+/// the search expires
+///
+/// Checked on every transition, so that a single `advance` of term stream
+/// over a long run of non-matching terms is bounded as well.
+struct BudgetedRegex<'a> {
+    regex: &'a Regex,
+    expires_at: Instant,
+    expired: Cell<bool>,
+}
+
+impl BudgetedRegex<'_> {
+    fn check_expired(&self) -> bool {
+        if !self.expired.get() && (Instant::now() >= self.expires_at || Deadline::current_expired())
+        {
+            self.expired.set(true);
+        }
+        self.expired.get()
+    }
+}
+
+impl Automaton for BudgetedRegex<'_> {
+    type State = Option<usize>;
+
+    fn start(&self) -> Self::State {
+        self.regex.start()
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        self.regex.is_match(state)
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        !self.check_expired() && self.regex.can_match(state)
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        if self.check_expired() {
+            return None;
+        }
+        self.regex.accept(state, byte)
+    }
+}
+
+/// Query of regex expanded into terms, see [`expand_regex`]
+pub struct RegexExpansion {
+    pub query: Box<dyn Query>,
+    /// Whether expansion stopped early since a limit is hit, so that some
+    /// matched terms are missing from query
+    pub partial: bool,
+}
+
+/// Expand regex into terms of each field matching it, and make a union of
+/// term queries.
+///
+/// Instead of walking the whole term dictionary as `RegexQuery` does, only
+/// terms starting with the literal prefix of regex are visited. Expansion
+/// stops once `max_terms` distinct terms are found, `expires_at` is reached
+/// or deadline of the search expires, and the expansion is marked partial.
+/// Regexes expanded for the same request should share `expires_at`.
+pub fn expand_regex(
+    searcher: &Searcher,
+    fields: &[Field],
+    pattern: &str,
+    max_terms: usize,
+    max_automaton_size: usize,
+    expires_at: Instant,
+) -> Result<RegexExpansion, UserError> {
+    let regex = build_regex(pattern, max_automaton_size)?;
+    let automaton = BudgetedRegex {
+        regex: &regex,
+        expires_at,
+        expired: Cell::new(false),
+    };
+    let prefix = regex_literal_prefix(pattern).unwrap_or_default();
+    let upper_bound = prefix_successor(prefix.as_bytes());
+
+    let mut terms = BTreeSet::new();
+    let mut partial = false;
+    'expansion: for field in fields {
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader
+                .inverted_index(*field)
+                .map_err(|tantivy_error| UserError::UnexpectedTantivy { tantivy_error })?;
+            let mut stream_builder = inverted_index
+                .terms()
+                .search(&automaton)
+                .ge(prefix.as_bytes());
+            if let Some(upper_bound) = &upper_bound {
+                stream_builder = stream_builder.lt(upper_bound);
+            }
+            let mut stream = stream_builder.into_stream().map_err(|error| {
+                UserError::Unexpected(format!("Stream term dictionary failed: {}", error))
+            })?;
+            while stream.advance() {
+                if automaton.expired.get() {
+                    break 'expansion;
+                }
+                if let Ok(text) = std::str::from_utf8(stream.key()) {
+                    let term = Term::from_field_text(*field, text);
+                    if terms.len() >= max_terms && !terms.contains(&term) {
+                        partial = true;
+                        break 'expansion;
+                    }
+                    terms.insert(term);
+                }
+            }
+        }
+    }
+
+    partial |= automaton.expired.get();
+    let subqueries = terms
+        .into_iter()
+        .map(|term| Box::new(TermQuery::new(term, IndexRecordOption::WithFreqs)) as Box<dyn Query>)
+        .collect();
+    Ok(RegexExpansion {
+        query: Box::new(BooleanQuery::union(subqueries)),
+        partial,
+    })
+}
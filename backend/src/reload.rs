@@ -0,0 +1,180 @@
+use super::config::{Config, ConfigError, ConfigSource};
+use super::logger;
+use super::model::UdiabModel;
+use super::rate_limit::RateLimiter;
+use actix_web::web;
+use std::collections::BTreeMap;
+use std::fmt;
+use toml::Value;
+
+/// Settings applied by [`Reloader::reload`], either a whole section or a
+/// single setting. Changes of other settings take effect after restart
+const RELOADABLE_SETTINGS: &[&str] = &[
+    "log_level",
+    "popularity.click_weight",
+    "synonyms.boost",
+    "cache",
+    "limits",
+    "rate_limit",
+];
+
+/// Settings whose values are never logged
+const SECRET_SETTINGS: &[&str] = &["auth"];
+
+/// A setting changed by reload
+pub struct SettingChange {
+    pub name: String,
+    old: Option<Value>,
+    new: Option<Value>,
+    /// Whether the change is applied without restart
+    pub reloadable: bool,
+}
+
+impl fmt::Display for SettingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let display = |value: &Option<Value>| match value {
+            _ if is_under(&self.name, SECRET_SETTINGS) => "***".to_string(),
+            Some(value) => value.to_string(),
+            None => "(none)".to_string(),
+        };
+        write!(
+            f,
+            "{}: {} -> {}",
+            self.name,
+            display(&self.old),
+            display(&self.new)
+        )?;
+        if !self.reloadable {
+            write!(f, " (requires restart)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether setting `name` is one of `settings` or in one of them
+fn is_under(name: &str, settings: &[&str]) -> bool {
+    settings.iter().any(|setting| {
+        name == *setting
+            || name
+                .strip_prefix(setting)
+                .is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+/// Flatten settings into dotted names, e.g., `cache.ttl`. Arrays are single
+/// settings
+fn flatten(value: Value, name: String, settings: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Table(table) => {
+            for (key, value) in table {
+                let name = if name.is_empty() {
+                    key
+                } else {
+                    format!("{}.{}", name, key)
+                };
+                flatten(value, name, settings);
+            }
+        }
+        value => {
+            settings.insert(name, value);
+        }
+    }
+}
+
+/// Settings differing between `old` and `new`
+pub fn changed_settings(old: &Config, new: &Config) -> Vec<SettingChange> {
+    let settings = |config: &Config| {
+        let mut settings = BTreeMap::new();
+        if let Ok(value) = Value::try_from(config) {
+            flatten(value, String::new(), &mut settings);
+        }
+        settings
+    };
+    let mut old_settings = settings(old);
+    let mut new_settings = settings(new);
+    let mut names = old_settings.keys().cloned().collect::<Vec<_>>();
+    names.extend(
+        new_settings
+            .keys()
+            .filter(|name| !old_settings.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>(),
+    );
+    names.sort();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let old = old_settings.remove(&name);
+            let new = new_settings.remove(&name);
+            (old != new).then(|| SettingChange {
+                reloadable: is_under(&name, RELOADABLE_SETTINGS),
+                name,
+                old,
+                new,
+            })
+        })
+        .collect()
+}
+
+/// Re-reads config (e.g., on SIGHUP) and applies reloadable settings to
+/// running components without dropping connections
+pub struct Reloader {
+    source: ConfigSource,
+    /// Config in effect
+    config: Config,
+    udiab_model: web::Data<UdiabModel>,
+    rate_limiter: web::Data<RateLimiter>,
+}
+
+impl Reloader {
+    pub fn new(
+        source: ConfigSource,
+        config: Config,
+        udiab_model: web::Data<UdiabModel>,
+        rate_limiter: web::Data<RateLimiter>,
+    ) -> Self {
+        Self {
+            source,
+            config,
+            udiab_model,
+            rate_limiter,
+        }
+    }
+
+    /// Re-read config with env overrides in `vars`, apply reloadable
+    /// settings, and return all changed settings
+    ///
+    /// If new config is invalid, nothing is applied.
+    pub fn reload(
+        &mut self,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Vec<SettingChange>, ConfigError> {
+        let new_config = self.source.load(vars)?;
+        let changes = changed_settings(&self.config, &new_config);
+
+        logger::set_level(new_config.log_level());
+        self.udiab_model
+            .popularity
+            .set_click_weight(new_config.popularity.click_weight);
+        self.udiab_model
+            .synonyms
+            .set_boost(new_config.synonyms.boost);
+        self.udiab_model.resize_caches(&new_config.cache);
+        self.udiab_model.set_limits(new_config.limits.clone());
+        self.rate_limiter.reconfigure(new_config.rate_limit.clone());
+
+        // Settings requiring restart stay as they are, so that their changes
+        // are reported until restart
+        self.config.log_level = new_config.log_level;
+        self.config.popularity.click_weight = new_config.popularity.click_weight;
+        self.config.synonyms.boost = new_config.synonyms.boost;
+        self.config.cache = new_config.cache;
+        self.config.limits = new_config.limits;
+        self.config.rate_limit = new_config.rate_limit;
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+#[path = "./reload_test.rs"]
+mod reload_test;
@@ -0,0 +1,94 @@
+use super::*;
+use crate::config::{AuthConfig, CacheConfig, LimitsConfig, RateLimitConfig};
+use crate::popularity::Popularity;
+use crate::synonyms::Synonyms;
+use std::io::Write;
+use std::sync::Arc;
+
+#[test]
+fn test_changed_settings() {
+    let old = Config::default();
+    let mut new = Config {
+        port: 8080,
+        ..Config::default()
+    };
+    new.cache.ttl = 5;
+    new.auth.api_keys = vec!["secret:admin".parse().unwrap()];
+    let changes = changed_settings(&old, &new);
+    let got = changes
+        .iter()
+        .map(|change| (change.name.as_str(), change.reloadable))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        got,
+        vec![
+            ("auth.api_keys", false),
+            ("cache.ttl", true),
+            ("port", false)
+        ]
+    );
+    assert_eq!(changes[1].to_string(), "cache.ttl: 60 -> 5");
+    assert!(!changes[0].to_string().contains("secret"));
+    assert!(changed_settings(&old, &Config::default()).is_empty());
+}
+
+#[test]
+fn test_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let (index, project_document) = search_base::index(dir.path()).unwrap();
+    let udiab_model = web::Data::new(UdiabModel::new(
+        index.reader().unwrap(),
+        project_document,
+        Arc::new(Popularity::open(None, 10).unwrap()),
+        Arc::new(Synonyms::open(None, 0.5).unwrap()),
+        &CacheConfig::default(),
+        LimitsConfig::default(),
+    ));
+    let rate_limiter = web::Data::new(RateLimiter::new(
+        RateLimitConfig::default(),
+        &AuthConfig::default(),
+    ));
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let source =
+        ConfigSource::from_args(vec![format!("--config={}", file.path().display())]).unwrap();
+    let mut reloader = Reloader::new(source, Config::default(), udiab_model.clone(), rate_limiter);
+
+    file.write_all(b"port = 8080\n[cache]\ncapacity = 16\n[limits]\nmax_page_size = 5\n")
+        .unwrap();
+    let changes = reloader.reload(vec![]).unwrap();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(udiab_model.limits().max_page_size, 5);
+    assert_eq!(udiab_model.cache_stats().retrieved_info.capacity, 16);
+    let version = udiab_model.popularity.version();
+
+    // Port is reported until restart, reloaded settings are not
+    let changes = reloader
+        .reload(vec![(
+            "UDIAB_POPULARITY__CLICK_WEIGHT".to_string(),
+            "20".to_string(),
+        )])
+        .unwrap();
+    let got = changes
+        .iter()
+        .map(|change| change.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(got, vec!["popularity.click_weight", "port"]);
+    assert!(udiab_model.popularity.version() > version);
+
+    // Invalid config applies nothing
+    let got = reloader.reload(vec![(
+        "UDIAB_LIMITS__MAX_PAGE_SIZE".to_string(),
+        "0".to_string(),
+    )]);
+    assert!(got.is_err());
+    assert_eq!(udiab_model.limits().max_page_size, 5);
+
+    let changes = reloader
+        .reload(vec![("UDIAB_LOG_LEVEL".to_string(), "warn".to_string())])
+        .unwrap();
+    assert!(changes
+        .iter()
+        .any(|change| change.name == "log_level" && change.reloadable));
+    assert_eq!(log::max_level(), log::LevelFilter::Warn);
+}
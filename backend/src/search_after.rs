@@ -0,0 +1,279 @@
+use super::interfaces::{SortOrder, SortSpec, UserError};
+use super::popularity::{HotScorer, HotSegmentScorer};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BinaryHeap;
+use std::hash::{Hash, Hasher};
+use tantivy::{
+    collector::{Collector, CustomScorer, CustomSegmentScorer, SegmentCollector},
+    fastfield::{DynamicFastFieldReader, FastFieldReader},
+    schema::Field,
+    DocAddress, DocId, Score, SegmentOrdinal, SegmentReader,
+};
+
+/// Value which hits are sorted by, in descending order
+///
+/// Values of ascending sort keys are flipped, so that the greater is always
+/// the better.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, PartialOrd, Debug)]
+#[serde(rename_all = "camelCase")]
+pub enum SortValue {
+    /// Relevance score
+    Score(Score),
+    /// Hot score, or u64 representation of fast field
+    Value(u64),
+}
+
+impl SortValue {
+    /// Flip value so that the order is reversed
+    fn flip(self) -> Self {
+        match self {
+            SortValue::Score(score) => SortValue::Score(-score),
+            SortValue::Value(value) => SortValue::Value(u64::MAX - value),
+        }
+    }
+}
+
+/// Position of the last hit of a page, used to fetch the next page
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchCursor {
+    /// Fingerprint of sort list this cursor is created with,
+    /// see [`sort_fingerprint`]
+    pub sort: u64,
+    pub values: Vec<SortValue>,
+    pub segment_ord: u32,
+    pub doc_id: u32,
+    /// Generation of searcher this cursor is created with, mixed with
+    /// version of popularity if sorted by hot.
+    ///
+    /// Doc address is meaningless in searcher of another generation, and so
+    /// are hot scores of another popularity version.
+    pub generation: u64,
+}
+
+impl SearchCursor {
+    /// Encode cursor as an opaque URL-safe string
+    pub fn encode(&self) -> String {
+        base64::encode_config(
+            serde_json::to_vec(self).unwrap_or_default(),
+            base64::URL_SAFE_NO_PAD,
+        )
+    }
+
+    /// Decode cursor from string returned by [`SearchCursor::encode`]
+    pub fn decode(encoded: &str) -> Result<Self, UserError> {
+        base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| UserError::BadRequest("Malformed cursor".to_string()))
+    }
+}
+
+/// Fingerprint of sort list stored in cursor
+pub fn sort_fingerprint(sort: &[SortSpec]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    sort.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// What hits are sorted by
+pub enum SortKey {
+    /// Relevance score of query
+    Relevance,
+    /// u64 representation of a fast field
+    FastField(Field),
+    /// Scraped likes blended with clicks
+    Hot(HotScorer),
+}
+
+/// A collected hit. The greater, the better.
+#[derive(Clone)]
+pub struct Hit {
+    values: Vec<SortValue>,
+    doc_address: DocAddress,
+}
+
+impl Hit {
+    /// Higher values first (compared in order of sort keys), then lower doc
+    /// address first
+    fn compare(&self, other: &Self) -> Ordering {
+        self.values
+            .partial_cmp(&other.values)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.doc_address.cmp(&self.doc_address))
+    }
+}
+
+impl PartialEq for Hit {
+    fn eq(&self, other: &Self) -> bool {
+        self.compare(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Hit {}
+
+impl PartialOrd for Hit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Hit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare(other)
+    }
+}
+
+/// Top docs collector supporting both offset and search-after pagination
+///
+/// Hits are sorted by each sort key in turn, and ties are broken by doc
+/// address in ascending order, so that the order is total and a cursor
+/// pointing to the last hit determines the next page exactly.
+pub struct SearchAfterCollector {
+    sort_keys: Vec<(SortKey, SortOrder)>,
+    limit: usize,
+    offset: usize,
+    after: Option<Hit>,
+}
+
+impl SearchAfterCollector {
+    pub fn new(sort_keys: Vec<(SortKey, SortOrder)>, limit: usize, offset: usize) -> Self {
+        Self {
+            sort_keys,
+            limit,
+            offset,
+            after: None,
+        }
+    }
+
+    /// Only collect hits strictly after given cursor
+    pub fn after(mut self, cursor: &SearchCursor) -> Self {
+        self.after = Some(Hit {
+            values: cursor.values.clone(),
+            doc_address: DocAddress {
+                segment_ord: cursor.segment_ord,
+                doc_id: cursor.doc_id,
+            },
+        });
+        self
+    }
+}
+
+enum SegmentSortKey {
+    Relevance,
+    FastField(DynamicFastFieldReader<u64>),
+    Hot(HotSegmentScorer),
+}
+
+pub struct SearchAfterSegmentCollector {
+    sort_keys: Vec<(SegmentSortKey, SortOrder)>,
+    segment_ord: SegmentOrdinal,
+    capacity: usize,
+    after: Option<Hit>,
+    /// Min-heap of best hits
+    heap: BinaryHeap<Reverse<Hit>>,
+}
+
+impl Collector for SearchAfterCollector {
+    type Fruit = Vec<(Vec<SortValue>, DocAddress)>;
+    type Child = SearchAfterSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_ord: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        let sort_keys = self
+            .sort_keys
+            .iter()
+            .map(|(sort_key, order)| {
+                let segment_sort_key = match sort_key {
+                    SortKey::Relevance => SegmentSortKey::Relevance,
+                    SortKey::FastField(field) => {
+                        SegmentSortKey::FastField(segment_reader.fast_fields().u64_lenient(*field)?)
+                    }
+                    SortKey::Hot(hot_scorer) => {
+                        SegmentSortKey::Hot(hot_scorer.segment_scorer(segment_reader)?)
+                    }
+                };
+                Ok((segment_sort_key, *order))
+            })
+            .collect::<tantivy::Result<Vec<_>>>()?;
+        Ok(SearchAfterSegmentCollector {
+            sort_keys,
+            segment_ord,
+            capacity: self.limit + self.offset,
+            after: self.after.clone(),
+            heap: BinaryHeap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.sort_keys
+            .iter()
+            .any(|(sort_key, _)| matches!(sort_key, SortKey::Relevance))
+    }
+
+    fn merge_fruits(&self, segment_fruits: Vec<Vec<Hit>>) -> tantivy::Result<Self::Fruit> {
+        let mut hits = segment_fruits.into_iter().flatten().collect::<Vec<_>>();
+        hits.sort_by(|hit, other| other.cmp(hit));
+        Ok(hits
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .map(|hit| (hit.values, hit.doc_address))
+            .collect())
+    }
+}
+
+impl SegmentCollector for SearchAfterSegmentCollector {
+    type Fruit = Vec<Hit>;
+
+    fn collect(&mut self, doc_id: DocId, score: Score) {
+        if self.capacity == 0 {
+            return;
+        }
+        let values = self
+            .sort_keys
+            .iter_mut()
+            .map(|(sort_key, order)| {
+                let value = match sort_key {
+                    SegmentSortKey::Relevance => SortValue::Score(score),
+                    SegmentSortKey::FastField(reader) => SortValue::Value(reader.get(doc_id)),
+                    SegmentSortKey::Hot(scorer) => SortValue::Value(scorer.score(doc_id)),
+                };
+                match order {
+                    SortOrder::Desc => value,
+                    SortOrder::Asc => value.flip(),
+                }
+            })
+            .collect();
+        let hit = Hit {
+            values,
+            doc_address: DocAddress {
+                segment_ord: self.segment_ord,
+                doc_id,
+            },
+        };
+        if let Some(after) = &self.after {
+            // Only hits worse than cursor are in later pages
+            if hit >= *after {
+                return;
+            }
+        }
+        if self.heap.len() < self.capacity {
+            self.heap.push(Reverse(hit));
+        } else if let Some(Reverse(worst)) = self.heap.peek() {
+            if hit > *worst {
+                self.heap.pop();
+                self.heap.push(Reverse(hit));
+            }
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.heap.into_iter().map(|Reverse(hit)| hit).collect()
+    }
+}
@@ -0,0 +1,103 @@
+use super::deadline::{CancelOnDrop, Deadline};
+use super::interfaces::UserError;
+use futures::channel::oneshot;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Time given to a search to return partial results after its deadline
+const CANCELLATION_GRACE: Duration = Duration::from_secs(1);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Bounded thread pool running searches off the async executor
+///
+/// At most `queue_size` searches wait for a thread; more are rejected
+/// instead of piling up.
+pub struct SearchPool {
+    sender: SyncSender<Job>,
+    /// Deadline of each search
+    pub timeout: Duration,
+}
+
+impl SearchPool {
+    pub fn new(threads: usize, queue_size: usize, timeout: Duration) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<Job>(queue_size);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for index in 0..threads.max(1) {
+            let receiver = receiver.clone();
+            thread::Builder::new()
+                .name(format!("search-{}", index))
+                .spawn(move || loop {
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => break,
+                    };
+                    match job {
+                        // A panicking search should not take the thread with it
+                        Ok(job) => {
+                            let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                        }
+                        Err(_) => break,
+                    }
+                })
+                .expect("Unable to spawn search thread");
+        }
+        Self { sender, timeout }
+    }
+
+    /// Run search on pool with given deadline
+    ///
+    /// The search is cancelled if the returned future is dropped, e.g.,
+    /// client disconnected. If the search does not return within a grace
+    /// period after deadline, [`UserError::Timeout`] is returned.
+    pub async fn run<R, F>(&self, deadline: &Arc<Deadline>, search: F) -> Result<R, UserError>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> Result<R, UserError> + Send + 'static,
+    {
+        let (result_sender, result_receiver) = oneshot::channel();
+        let job_deadline = deadline.clone();
+        let job = Box::new(move || {
+            // Cancelled or expired while waiting in queue
+            if job_deadline.is_expired() {
+                return;
+            }
+            let _ = result_sender.send(job_deadline.scope(search));
+        });
+        self.sender.try_send(job).map_err(|error| match error {
+            TrySendError::Full(_) => {
+                UserError::Unavailable("Too many searches are waiting".to_string())
+            }
+            TrySendError::Disconnected(_) => {
+                UserError::Unexpected("Search pool is shut down".to_string())
+            }
+        })?;
+
+        let cancel_on_drop = CancelOnDrop::new(deadline.clone());
+        let result =
+            actix_rt::time::timeout(deadline.remaining() + CANCELLATION_GRACE, result_receiver)
+                .await;
+        cancel_on_drop.disarm();
+        match result {
+            Ok(Ok(result)) => result,
+            // Job is dropped without result
+            Ok(Err(_)) if deadline.is_expired() => Err(UserError::Timeout(
+                "Search expired before it started".to_string(),
+            )),
+            Ok(Err(_)) => Err(UserError::Unexpected("Search panicked".to_string())),
+            Err(_) => {
+                deadline.cancel();
+                Err(UserError::Timeout(
+                    "Search did not finish in time".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./search_pool_test.rs"]
+mod search_pool_test;
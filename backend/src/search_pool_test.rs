@@ -0,0 +1,76 @@
+use super::*;
+use std::future::Future;
+use std::sync::mpsc;
+
+fn block_on<F: Future + 'static>(future: F) -> F::Output {
+    actix_rt::System::new("search_pool_test").block_on(future)
+}
+
+#[test]
+fn test_run_returns_result() {
+    let search_pool = SearchPool::new(1, 1, Duration::from_secs(5));
+    let deadline = Deadline::new(search_pool.timeout);
+    let got = block_on({
+        let deadline = deadline.clone();
+        async move { search_pool.run(&deadline, || Ok(42)).await }
+    });
+    assert_eq!(got.unwrap(), 42);
+    assert!(!deadline.is_expired());
+}
+
+#[test]
+fn test_run_with_full_queue() {
+    let search_pool = SearchPool::new(1, 1, Duration::from_secs(5));
+    let (started_sender, started_receiver) = mpsc::channel();
+    let (release_sender, release_receiver) = mpsc::channel::<()>();
+    let got = block_on(async move {
+        let deadline = Deadline::new(search_pool.timeout);
+        // Occupies the only thread
+        let blocking = search_pool.run(&deadline, move || {
+            started_sender.send(()).unwrap();
+            release_receiver.recv().ok();
+            Ok(())
+        });
+        futures::pin_mut!(blocking);
+        assert!(futures::poll!(blocking.as_mut()).is_pending());
+        started_receiver.recv().unwrap();
+        // Occupies the only slot in queue
+        let queued = search_pool.run(&deadline, || Ok(()));
+        futures::pin_mut!(queued);
+        assert!(futures::poll!(queued.as_mut()).is_pending());
+
+        let got = search_pool.run(&deadline, || Ok(())).await;
+        assert!(matches!(got, Err(UserError::Unavailable(_))));
+
+        release_sender.send(()).unwrap();
+        blocking.await.and(queued.await)
+    });
+    assert!(got.is_ok());
+}
+
+#[test]
+fn test_run_after_deadline() {
+    let search_pool = SearchPool::new(1, 1, Duration::from_secs(0));
+    let got = block_on(async move {
+        let deadline = Deadline::new(search_pool.timeout);
+        search_pool.run(&deadline, || Ok(())).await
+    });
+    assert!(matches!(got, Err(UserError::Timeout(_))));
+}
+
+#[test]
+fn test_run_sees_deadline() {
+    let search_pool = SearchPool::new(1, 1, Duration::from_secs(5));
+    let got = block_on(async move {
+        let deadline = Deadline::new(search_pool.timeout);
+        let job_deadline = deadline.clone();
+        search_pool
+            .run(&deadline, move || {
+                job_deadline.cancel();
+                Ok(Deadline::current_expired())
+            })
+            .await
+    });
+    assert!(got.unwrap());
+    assert!(Deadline::current().is_none());
+}
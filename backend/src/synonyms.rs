@@ -0,0 +1,265 @@
+use super::interfaces::{SearchMethod, UserError};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// Max count of variants a key is expanded into, since each variant is
+/// searched as a query of its own
+pub const MAX_SYNONYM_VARIANTS: usize = 4;
+
+/// Synonyms of each term, keyed by lowercase term
+type Dictionary = HashMap<String, Vec<String>>;
+
+/// Dictionary with what is precomputed on load for matching keys against it
+struct LoadedDictionary {
+    dictionary: Dictionary,
+    /// Length (in bytes) of the longest term, bounding substrings of key
+    /// looked up in dictionary
+    max_term_len: usize,
+}
+
+impl LoadedDictionary {
+    fn new(dictionary: Dictionary) -> Self {
+        let max_term_len = dictionary.keys().map(String::len).max().unwrap_or(0);
+        Self {
+            dictionary,
+            max_term_len,
+        }
+    }
+
+    /// Terms occurring in `key` case-insensitively, sorted so that variants
+    /// are stable across requests
+    ///
+    /// Only substrings of key up to the longest term are looked up, so that
+    /// cost depends on length of key instead of size of dictionary.
+    fn terms_in(&self, key: &str) -> BTreeSet<&str> {
+        let (lowercase, offsets) = lowercase_with_offsets(key);
+        let boundaries: Vec<usize> = offsets
+            .iter()
+            .enumerate()
+            .filter_map(|(index, offset)| offset.map(|_| index))
+            .collect();
+        let mut terms = BTreeSet::new();
+        for (position, &start) in boundaries.iter().enumerate() {
+            let ends = boundaries[position + 1..]
+                .iter()
+                .take_while(|&&end| end - start <= self.max_term_len);
+            for &end in ends {
+                if let Some((term, _)) = self.dictionary.get_key_value(&lowercase[start..end]) {
+                    terms.insert(term.as_str());
+                }
+            }
+        }
+        terms
+    }
+}
+
+/// Dictionary of synonyms and acronyms, expanding keys at query time
+///
+/// Each line of the dictionary file is a group of equivalent terms separated
+/// by commas, e.g., `k8s, kubernetes`. Empty lines and lines starting with
+/// `#` are ignored. Terms are matched case-insensitively.
+pub struct Synonyms {
+    dictionary: RwLock<Arc<LoadedDictionary>>,
+    /// Count of reloads, i.e., version of dictionary
+    version: AtomicU64,
+    /// Bits of boost of expanded variants relative to key
+    boost: AtomicU32,
+    /// Where dictionary is read. If `None`, no key is expanded
+    path: Option<PathBuf>,
+}
+
+impl Synonyms {
+    /// Create synonyms, loading dictionary from `path` if it exists
+    pub fn open(path: Option<PathBuf>, boost: f32) -> Result<Self, UserError> {
+        let dictionary = read_dictionary(&path)?;
+        Ok(Self {
+            dictionary: RwLock::new(Arc::new(LoadedDictionary::new(dictionary))),
+            version: AtomicU64::new(0),
+            boost: AtomicU32::new(boost.to_bits()),
+            path,
+        })
+    }
+
+    /// Re-read dictionary file, and return count of terms in it
+    ///
+    /// If the file fails to load, the previous dictionary is kept.
+    pub fn reload(&self) -> Result<usize, UserError> {
+        let dictionary = read_dictionary(&self.path)?;
+        let terms_count = dictionary.len();
+        if let Ok(mut current_dictionary) = self.dictionary.write() {
+            *current_dictionary = Arc::new(LoadedDictionary::new(dictionary));
+            self.version.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(terms_count)
+    }
+
+    /// Count of reloads, so that results cached before a reload are dropped
+    pub fn version(&self) -> u64 {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    /// Boost of expanded variants, which is below 1 so that articles
+    /// matching key itself rank first
+    pub fn boost(&self) -> f32 {
+        f32::from_bits(self.boost.load(Ordering::Relaxed))
+    }
+
+    pub fn set_boost(&self, boost: f32) {
+        self.boost.store(boost.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Variants of `key` with all occurrences of a term replaced by one of
+    /// its synonyms, escaped as literals of `search_method`
+    ///
+    /// Latin terms only match whole words, e.g., `js` does not match in
+    /// `nodejs`. At most [`MAX_SYNONYM_VARIANTS`] variants are returned.
+    pub fn expand(&self, key: &str, search_method: SearchMethod) -> Vec<String> {
+        let loaded_dictionary = match self.dictionary.read() {
+            Ok(loaded_dictionary) => loaded_dictionary.clone(),
+            Err(_) => return vec![],
+        };
+        let mut variants: Vec<String> = vec![];
+        for term in loaded_dictionary.terms_in(key) {
+            // Occurrences of a term may all be parts of longer words
+            let occurrences = find_term(key, term);
+            if occurrences.is_empty() {
+                continue;
+            }
+            for synonym in &loaded_dictionary.dictionary[term] {
+                let replacement = escape(synonym, search_method);
+                let mut variant = String::with_capacity(key.len());
+                let mut last_end = 0;
+                for &(start, end) in &occurrences {
+                    variant.push_str(&key[last_end..start]);
+                    variant.push_str(&replacement);
+                    last_end = end;
+                }
+                variant.push_str(&key[last_end..]);
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+                if variants.len() == MAX_SYNONYM_VARIANTS {
+                    return variants;
+                }
+            }
+        }
+        variants
+    }
+}
+
+/// Read dictionary at `path`, which is empty if there is no such file
+fn read_dictionary(path: &Option<PathBuf>) -> Result<Dictionary, UserError> {
+    match path {
+        Some(path) if path.exists() => {
+            let content = fs::read_to_string(path).map_err(|error| {
+                UserError::Unexpected(format!("Read synonyms failed: {}", error))
+            })?;
+            Ok(parse_dictionary(&content))
+        }
+        _ => Ok(Dictionary::new()),
+    }
+}
+
+/// Parse groups of equivalent terms, see [`Synonyms`]
+///
+/// A term in several groups has synonyms of all of them.
+pub fn parse_dictionary(content: &str) -> Dictionary {
+    let mut dictionary = Dictionary::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut group: Vec<&str> = vec![];
+        for term in line.split(',').map(str::trim) {
+            if !term.is_empty() && !group.iter().any(|other| eq_ignore_case(other, term)) {
+                group.push(term);
+            }
+        }
+        for term in &group {
+            let synonyms = dictionary.entry(term.to_lowercase()).or_default();
+            for synonym in &group {
+                if !eq_ignore_case(synonym, term)
+                    && !synonyms.iter().any(|other| eq_ignore_case(other, synonym))
+                {
+                    synonyms.push(synonym.to_string());
+                }
+            }
+        }
+    }
+    dictionary.retain(|_, synonyms| !synonyms.is_empty());
+    dictionary
+}
+
+fn eq_ignore_case(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Lowercase copy of `key`, together with the byte offset in `key` of each
+/// byte of the copy
+///
+/// The offset is `None` unless a char of `key` starts there, e.g., inside
+/// the lowercase of `İ`, which is longer than `İ` itself. The offset past
+/// the copy is the length of `key`.
+fn lowercase_with_offsets(key: &str) -> (String, Vec<Option<usize>>) {
+    let mut lowercase = String::with_capacity(key.len());
+    let mut offsets = Vec::with_capacity(key.len() + 1);
+    for (offset, c) in key.char_indices() {
+        offsets.push(Some(offset));
+        lowercase.extend(c.to_lowercase());
+        offsets.resize(lowercase.len(), None);
+    }
+    offsets.push(Some(key.len()));
+    (lowercase, offsets)
+}
+
+/// Byte ranges in `key` of non-overlapping occurrences of lowercase `term`
+/// in lowercase of `key`
+fn find_term(key: &str, term: &str) -> Vec<(usize, usize)> {
+    let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_ascii_alphanumeric());
+    let needs_boundary_before = is_word_char(term.chars().next());
+    let needs_boundary_after = is_word_char(term.chars().next_back());
+    let (lowercase, offsets) = lowercase_with_offsets(key);
+    let mut occurrences = vec![];
+    let mut search_from = 0;
+    while search_from < lowercase.len() {
+        let start = match lowercase[search_from..].find(term) {
+            Some(index) => search_from + index,
+            None => break,
+        };
+        let end = start + term.len();
+        // Occurrence must span whole chars of key
+        let occurrence = offsets[start]
+            .zip(offsets[end])
+            .filter(|&(key_start, key_end)| {
+                (!needs_boundary_before || !is_word_char(key[..key_start].chars().next_back()))
+                    && (!needs_boundary_after || !is_word_char(key[key_end..].chars().next()))
+            });
+        if let Some(occurrence) = occurrence {
+            occurrences.push(occurrence);
+            search_from = end;
+        } else {
+            search_from = start + lowercase[start..].chars().next().map_or(1, char::len_utf8);
+        }
+    }
+    occurrences
+}
+
+/// Escape `synonym` as a literal of key of `search_method`
+fn escape(synonym: &str, search_method: SearchMethod) -> String {
+    match search_method {
+        SearchMethod::Naive => synonym.to_string(),
+        SearchMethod::Complex if synonym.contains(char::is_whitespace) => {
+            format!("\"{}\"", synonym)
+        }
+        SearchMethod::Complex => synonym.to_string(),
+        SearchMethod::Regex => regex::escape(synonym),
+    }
+}
+
+#[cfg(test)]
+#[path = "./synonyms_test.rs"]
+mod synonyms_test;
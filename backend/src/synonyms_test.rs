@@ -0,0 +1,112 @@
+use super::*;
+
+fn synonyms(content: &str) -> Synonyms {
+    Synonyms {
+        dictionary: RwLock::new(Arc::new(LoadedDictionary::new(parse_dictionary(content)))),
+        version: AtomicU64::new(0),
+        boost: AtomicU32::new(0.5f32.to_bits()),
+        path: None,
+    }
+}
+
+#[test]
+fn test_parse_dictionary() {
+    let dictionary = parse_dictionary(
+        "# comment\n\
+         k8s, Kubernetes\n\
+         \n\
+         js,JavaScript, JS\n\
+         数据库, DB\n\
+         db, database\n\
+         lonely\n",
+    );
+    assert_eq!(dictionary["k8s"], vec!["Kubernetes"]);
+    assert_eq!(dictionary["kubernetes"], vec!["k8s"]);
+    assert_eq!(dictionary["js"], vec!["JavaScript"]);
+    assert_eq!(dictionary["db"], vec!["数据库", "database"]);
+    assert!(!dictionary.contains_key("lonely"));
+    assert!(!dictionary.contains_key("# comment"));
+}
+
+#[test]
+fn test_expand() {
+    let synonyms = synonyms("k8s, kubernetes\njs, javascript\n数据库, DB\n");
+    assert_eq!(
+        synonyms.expand("K8S 部署 k8s", SearchMethod::Naive),
+        vec!["kubernetes 部署 kubernetes"]
+    );
+    // Latin terms only match whole words
+    assert!(synonyms.expand("nodejs", SearchMethod::Naive).is_empty());
+    assert_eq!(
+        synonyms.expand("node js", SearchMethod::Naive),
+        vec!["node javascript"]
+    );
+    assert_eq!(
+        synonyms.expand("mysql数据库连接", SearchMethod::Naive),
+        vec!["mysqlDB连接"]
+    );
+    assert_eq!(
+        synonyms.expand("java", SearchMethod::Naive),
+        Vec::<String>::new()
+    );
+}
+
+#[test]
+fn test_terms_in() {
+    let loaded_dictionary = LoadedDictionary::new(parse_dictionary(
+        "k8s, kubernetes\njs, javascript\n数据库, DB\n",
+    ));
+    assert_eq!(loaded_dictionary.max_term_len, "kubernetes".len());
+    let got = loaded_dictionary.terms_in("JS 连接数据库 K8s");
+    assert_eq!(
+        got.into_iter().collect::<Vec<_>>(),
+        vec!["js", "k8s", "数据库"]
+    );
+    // Whole words are checked later by `find_term`
+    let got = loaded_dictionary.terms_in("nodejs");
+    assert_eq!(got.into_iter().collect::<Vec<_>>(), vec!["js"]);
+    assert!(loaded_dictionary.terms_in("").is_empty());
+}
+
+#[test]
+fn test_expand_lowercase_longer_than_key() {
+    // Lowercase of `İ` takes 3 bytes instead of 2
+    let synonyms = synonyms(
+        "k8s, kubernetes
+İstanbul, constantinople
+",
+    );
+    assert_eq!(
+        synonyms.expand("İİ K8S", SearchMethod::Naive),
+        vec!["İİ kubernetes"]
+    );
+    assert_eq!(
+        synonyms.expand("İstanbul k8s", SearchMethod::Naive),
+        vec!["constantinople k8s", "İstanbul kubernetes"]
+    );
+    assert_eq!(find_term("İK8S", "k8s"), vec![(2, 5)]);
+    // Occurrence must not end inside lowercase of a char
+    assert_eq!(find_term("İ", "i"), vec![]);
+}
+
+#[test]
+fn test_expand_escaped() {
+    let synonyms = synonyms("c++, cpp\nk8s, kubernetes cluster\n");
+    assert_eq!(
+        synonyms.expand("cpp.*", SearchMethod::Regex),
+        vec!["c\\+\\+.*"]
+    );
+    assert_eq!(
+        synonyms.expand("title:k8s", SearchMethod::Complex),
+        vec!["title:\"kubernetes cluster\""]
+    );
+}
+
+#[test]
+fn test_expand_limited() {
+    let synonyms = synonyms("a, b, c, d, e, f, g\n");
+    assert_eq!(
+        synonyms.expand("a", SearchMethod::Naive).len(),
+        MAX_SYNONYM_VARIANTS
+    );
+}
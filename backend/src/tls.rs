@@ -0,0 +1,144 @@
+use super::config::TlsConfig;
+use actix_web::{http::header, HttpRequest, HttpResponse};
+use rustls::internal::pemfile;
+use rustls::sign::{self, CertifiedKey};
+use rustls::{ClientHello, NoClientAuth, ResolvesServerCert, ServerConfig};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Certificate and key in use, together with modification times of their
+/// files when loaded
+struct LoadedCertificate {
+    certified_key: CertifiedKey,
+    modified: (Option<SystemTime>, Option<SystemTime>),
+    checked: Instant,
+}
+
+/// Resolves the certificate of [`TlsConfig`], reloading it once its files
+/// change
+///
+/// Files are checked at most once per `reload_interval` seconds during
+/// handshakes. If a changed certificate fails to load, e.g., it is only
+/// partially written, the previous one is kept and loading is retried at
+/// next check.
+pub struct CertificateResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    reload_interval: Duration,
+    loaded: RwLock<LoadedCertificate>,
+}
+
+impl CertificateResolver {
+    pub fn new(config: &TlsConfig) -> Result<Self, String> {
+        let cert_path = PathBuf::from(&config.cert_path);
+        let key_path = PathBuf::from(&config.key_path);
+        let modified = (modified_time(&cert_path), modified_time(&key_path));
+        let certified_key = load_certified_key(&cert_path, &key_path)?;
+        Ok(Self {
+            cert_path,
+            key_path,
+            reload_interval: Duration::from_secs(config.reload_interval),
+            loaded: RwLock::new(LoadedCertificate {
+                certified_key,
+                modified,
+                checked: Instant::now(),
+            }),
+        })
+    }
+
+    /// Certificate to present, reloaded if its files changed
+    pub fn certified_key(&self) -> Option<CertifiedKey> {
+        {
+            let loaded = self.loaded.read().ok()?;
+            if loaded.checked.elapsed() < self.reload_interval {
+                return Some(loaded.certified_key.clone());
+            }
+        }
+        let mut loaded = self.loaded.write().ok()?;
+        loaded.checked = Instant::now();
+        let modified = (
+            modified_time(&self.cert_path),
+            modified_time(&self.key_path),
+        );
+        if modified != loaded.modified {
+            match load_certified_key(&self.cert_path, &self.key_path) {
+                Ok(certified_key) => {
+                    log::info!("TLS certificate reloaded");
+                    loaded.certified_key = certified_key;
+                    loaded.modified = modified;
+                }
+                Err(error) => log::error!("TLS certificate reload failed: {}", error),
+            }
+        }
+        Some(loaded.certified_key.clone())
+    }
+
+    /// Server config presenting certificate of this resolver
+    pub fn server_config(self) -> ServerConfig {
+        let mut server_config = ServerConfig::new(NoClientAuth::new());
+        server_config.cert_resolver = Arc::new(self);
+        server_config
+    }
+}
+
+impl ResolvesServerCert for CertificateResolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<CertifiedKey> {
+        self.certified_key()
+    }
+}
+
+fn modified_time(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Load PEM certificate chain at `cert_path` and PKCS#8 or RSA private key
+/// at `key_path`
+fn load_certified_key(cert_path: &PathBuf, key_path: &PathBuf) -> Result<CertifiedKey, String> {
+    let open = |path: &PathBuf| {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|error| format!("{}: {}", path.display(), error))
+    };
+    let certs = pemfile::certs(&mut open(cert_path)?)
+        .ok()
+        .filter(|certs| !certs.is_empty())
+        .ok_or_else(|| format!("{}: no PEM certificate", cert_path.display()))?;
+    let mut keys = pemfile::pkcs8_private_keys(&mut open(key_path)?).unwrap_or_default();
+    if keys.is_empty() {
+        keys = pemfile::rsa_private_keys(&mut open(key_path)?).unwrap_or_default();
+    }
+    let key = keys
+        .first()
+        .and_then(|key| sign::any_supported_type(key).ok())
+        .ok_or_else(|| format!("{}: no supported PEM private key", key_path.display()))?;
+    Ok(CertifiedKey::new(certs, Arc::new(key)))
+}
+
+/// Permanently redirect `request` to the same URL on HTTPS port
+/// `https_port`
+pub fn redirect_to_https(request: &HttpRequest, https_port: u16) -> HttpResponse {
+    let connection_info = request.connection_info();
+    let host = connection_info.host();
+    // Strip port of host, keeping brackets of IPv6 addresses
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if !name.is_empty() && !port.contains(']') => name,
+        _ => host,
+    };
+    let location = if https_port == 443 {
+        format!("https://{}{}", host, request.uri())
+    } else {
+        format!("https://{}:{}{}", host, https_port, request.uri())
+    };
+    HttpResponse::PermanentRedirect()
+        .set_header(header::LOCATION, location)
+        .finish()
+}
+
+#[cfg(test)]
+#[path = "./tls_test.rs"]
+mod tls_test;
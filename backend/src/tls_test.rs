@@ -0,0 +1,67 @@
+use super::*;
+use actix_web::test;
+use std::time::UNIX_EPOCH;
+
+/// Write a new self-signed certificate and key with `modified` time,
+/// returning the certificate in DER
+fn write_certificate(config: &TlsConfig, modified: u64) -> Vec<u8> {
+    let certificate = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let modified = UNIX_EPOCH + Duration::from_secs(modified);
+    // Signatures are randomized, so the certificate is serialized only once
+    let cert_pem = certificate.serialize_pem().unwrap();
+    for (path, pem) in [
+        (&config.cert_path, cert_pem.clone()),
+        (&config.key_path, certificate.serialize_private_key_pem()),
+    ] {
+        fs::write(path, pem).unwrap();
+        File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+    pemfile::certs(&mut cert_pem.as_bytes())
+        .unwrap()
+        .remove(0)
+        .0
+}
+
+#[test]
+fn test_certificate_reload() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = TlsConfig {
+        enabled: true,
+        cert_path: dir.path().join("cert.pem").display().to_string(),
+        key_path: dir.path().join("key.pem").display().to_string(),
+        reload_interval: 0,
+        ..TlsConfig::default()
+    };
+    assert!(CertificateResolver::new(&config).is_err());
+
+    let first = write_certificate(&config, 1);
+    let resolver = CertificateResolver::new(&config).unwrap();
+    assert_eq!(resolver.certified_key().unwrap().cert[0].0, first);
+
+    let second = write_certificate(&config, 2);
+    assert_eq!(resolver.certified_key().unwrap().cert[0].0, second);
+
+    // A broken certificate keeps the previous one
+    fs::write(&config.cert_path, "not a certificate").unwrap();
+    assert_eq!(resolver.certified_key().unwrap().cert[0].0, second);
+}
+
+#[test]
+fn test_redirect_to_https() {
+    let request = test::TestRequest::with_uri("/api/search?key=a")
+        .header(header::HOST, "udiab.example.com:8080")
+        .to_http_request();
+    for (https_port, expected) in [
+        (443, "https://udiab.example.com/api/search?key=a"),
+        (8443, "https://udiab.example.com:8443/api/search?key=a"),
+    ] {
+        let response = redirect_to_https(&request, https_port);
+        assert_eq!(response.status(), 308);
+        assert_eq!(response.headers().get(header::LOCATION).unwrap(), expected);
+    }
+}
@@ -0,0 +1,158 @@
+use super::interfaces::{AdvanceSearchOptions, SearchRequest, UserError};
+use chrono::{serde::ts_milliseconds::serialize as to_milli_ts, DateTime, Utc};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+/// Max count of events waiting to be written, beyond which events are
+/// dropped
+const TRACKING_QUEUE_SIZE: usize = 4096;
+
+/// Events recorded for computing click-through rate
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+pub enum TrackingEvent {
+    /// A page of results was shown to user
+    #[serde(rename_all = "camelCase")]
+    Impression {
+        impression_id: String,
+        key: String,
+        #[serde(flatten)]
+        advanced_search_options: AdvanceSearchOptions,
+        /// `None` if paginated by cursor
+        #[serde(skip_serializing_if = "Option::is_none")]
+        offset: Option<usize>,
+        page_size: usize,
+        /// URLs of shown articles, in the order they were shown.
+        ///
+        /// The position of the first url is `offset`, or the count of
+        /// articles shown before with the same cursor chain.
+        urls: Vec<String>,
+        #[serde(serialize_with = "to_milli_ts")]
+        time: DateTime<Utc>,
+    },
+    /// A page of results of query DSL was shown to user
+    #[serde(rename_all = "camelCase")]
+    SearchImpression {
+        impression_id: String,
+        request: SearchRequest,
+        /// URLs of shown articles, in the order they were shown
+        urls: Vec<String>,
+        #[serde(serialize_with = "to_milli_ts")]
+        time: DateTime<Utc>,
+    },
+    /// User opened one result of an impression
+    #[serde(rename_all = "camelCase")]
+    Click {
+        impression_id: String,
+        key: String,
+        /// Position of clicked article in the whole result list (0-based)
+        position: usize,
+        url: String,
+        #[serde(serialize_with = "to_milli_ts")]
+        time: DateTime<Utc>,
+    },
+}
+
+/// Persists tracking events to a JSON-lines file
+///
+/// Events are written by a dedicated thread, so that handlers recording them
+/// never block the async executor on file I/O. If writing stalls and
+/// [`TRACKING_QUEUE_SIZE`] events are waiting, further events are dropped
+/// and counted instead of piling up in memory.
+pub struct Tracker {
+    sender: Mutex<Option<SyncSender<String>>>,
+    writer_thread: Mutex<Option<JoinHandle<()>>>,
+    dropped: AtomicU64,
+}
+
+impl Tracker {
+    /// Open (or create) the tracking log at given path in append mode
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::with_writer(file, TRACKING_QUEUE_SIZE))
+    }
+
+    /// Write events to `writer`, with at most `queue_size` of them waiting
+    fn with_writer(writer: impl Write + Send + 'static, queue_size: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<String>(queue_size);
+        let writer_thread = thread::spawn(move || {
+            let mut writer = BufWriter::new(writer);
+            // Each event is flushed immediately, so that no event is lost
+            // when the backend is killed
+            for line in receiver {
+                if let Err(error) = writeln!(writer, "{}", line).and_then(|_| writer.flush()) {
+                    log::error!("Write tracking log failed: {}", error);
+                }
+            }
+        });
+        Self {
+            sender: Mutex::new(Some(sender)),
+            writer_thread: Mutex::new(Some(writer_thread)),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Queue an event to be appended to the tracking log, or drop it if the
+    /// queue is full
+    pub fn record(&self, event: &TrackingEvent) -> Result<(), UserError> {
+        let line = serde_json::to_string(event)
+            .map_err(|error| UserError::Unexpected(format!("Serialize event failed: {}", error)))?;
+        let sender = self
+            .sender
+            .lock()
+            .map_err(|_| UserError::Unexpected("Tracking log is poisoned".to_string()))?;
+        let sender = sender
+            .as_ref()
+            .ok_or_else(|| UserError::Unexpected("Tracking log is closed".to_string()))?;
+        match sender.try_send(line) {
+            Ok(()) => Ok(()),
+            // Tracking is best effort, the request itself succeeded
+            Err(TrySendError::Full(_)) => {
+                let dropped = self.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                if dropped % 1000 == 1 {
+                    log::warn!("Tracking log is falling behind, {} events dropped", dropped);
+                }
+                Ok(())
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                Err(UserError::Unexpected("Tracking log is closed".to_string()))
+            }
+        }
+    }
+
+    /// Count of events dropped since the queue was full
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Stop accepting events, and wait until queued ones are written
+    pub fn close(&self) {
+        if let Ok(mut sender) = self.sender.lock() {
+            sender.take();
+        }
+        let writer_thread = self
+            .writer_thread
+            .lock()
+            .ok()
+            .and_then(|mut writer_thread| writer_thread.take());
+        if let Some(writer_thread) = writer_thread {
+            if writer_thread.join().is_err() {
+                log::error!("Tracking log writer panicked");
+            }
+        }
+        let dropped = self.dropped();
+        if dropped > 0 {
+            log::warn!("{} tracking events were dropped in total", dropped);
+        }
+    }
+}
+
+#[cfg(test)]
+#[path = "./tracking_test.rs"]
+mod tracking_test;
@@ -0,0 +1,63 @@
+use super::*;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+
+/// Writer which reports each write, then blocks until released
+struct StalledWriter {
+    written: SyncSender<()>,
+    release: Arc<Mutex<Receiver<()>>>,
+    lines: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Write for StalledWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let _ = self.written.send(());
+        let _ = self.release.lock().unwrap().recv();
+        self.lines.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn click(position: usize) -> TrackingEvent {
+    TrackingEvent::Click {
+        impression_id: "impression".to_string(),
+        key: "key".to_string(),
+        position,
+        url: "url".to_string(),
+        time: Utc::now(),
+    }
+}
+
+#[test]
+fn test_record_drops_events_when_queue_is_full() {
+    let (written_sender, written) = mpsc::sync_channel(16);
+    let (release, release_receiver) = mpsc::channel();
+    let lines = Arc::new(Mutex::new(vec![]));
+    let tracker = Tracker::with_writer(
+        StalledWriter {
+            written: written_sender,
+            release: Arc::new(Mutex::new(release_receiver)),
+            lines: lines.clone(),
+        },
+        1,
+    );
+
+    tracker.record(&click(0)).unwrap();
+    // Writer took the first event and stalls
+    written.recv().unwrap();
+    tracker.record(&click(1)).unwrap();
+    tracker.record(&click(2)).unwrap();
+    tracker.record(&click(3)).unwrap();
+    assert_eq!(tracker.dropped(), 2);
+
+    // Let the writer go on with the rest
+    drop(release);
+    tracker.close();
+    let lines = String::from_utf8(lines.lock().unwrap().clone()).unwrap();
+    assert_eq!(lines.lines().count(), 2);
+    assert!(tracker.record(&click(4)).is_err());
+}
@@ -0,0 +1,202 @@
+use super::config::LimitsConfig;
+use super::interfaces::{
+    AggregationsRequest, AnalyzeRequest, ExplainRequest, ExportRequest, KeyHintsRequest,
+    MatchClause, MoreLikeThisRequest, QueryClause, RetrievedInfoRequest, SearchMethod,
+    SearchRequest, UserError,
+};
+use super::regex_expansion::regex_literal_prefix;
+use regex_syntax::hir::{Hir, HirKind, RepetitionKind, RepetitionRange};
+
+/// Requests checked against [`LimitsConfig`] before any search work
+pub trait Validate {
+    /// Returns [`UserError::BadRequest`] describing the first violated limit
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError>;
+}
+
+fn bad_request<T>(message: String) -> Result<T, UserError> {
+    Err(UserError::BadRequest(message))
+}
+
+fn validate_page(offset: usize, page_size: usize, limits: &LimitsConfig) -> Result<(), UserError> {
+    if page_size == 0 || page_size > limits.max_page_size {
+        return bad_request(format!(
+            "pageSize must be between 1 and {}, got {}",
+            limits.max_page_size, page_size
+        ));
+    }
+    if offset > limits.max_offset {
+        return bad_request(format!(
+            "offset must not exceed {}, got {}; use cursor for deeper pages",
+            limits.max_offset, offset
+        ));
+    }
+    Ok(())
+}
+
+fn validate_key(
+    key: &str,
+    search_method: SearchMethod,
+    limits: &LimitsConfig,
+) -> Result<(), UserError> {
+    if key.trim().is_empty() {
+        return bad_request("key must not be empty".to_string());
+    }
+    let key_length = key.chars().count();
+    if key_length > limits.max_key_length {
+        return bad_request(format!(
+            "key must not exceed {} chars, got {}",
+            limits.max_key_length, key_length
+        ));
+    }
+    // Only cheap checks of regex here, its automaton is built and checked
+    // against `max_regex_automaton_size` in the search pool
+    if search_method == SearchMethod::Regex {
+        let hir = regex_syntax::Parser::new()
+            .parse(key)
+            .or_else(|error| bad_request(format!("Invalid regex: {}", error)))?;
+        let complexity = regex_complexity(&hir);
+        if complexity > limits.max_regex_complexity {
+            return bad_request(format!(
+                "Regex is too complex: complexity must not exceed {}, got {}",
+                limits.max_regex_complexity, complexity
+            ));
+        }
+        if limits.require_regex_literal_prefix
+            && regex_literal_prefix(key).unwrap_or_default().is_empty()
+        {
+            return bad_request(
+                "Regex must start with a literal prefix, e.g., `abc.*` instead of `.*abc`"
+                    .to_string(),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Complexity of regex, which is roughly the size of its automaton
+///
+/// Each node of syntax tree counts one, and counted repetitions count their
+/// inner expression as many times as the upper bound (or lower bound plus
+/// one if unbounded), e.g., `a{1000}` is about 1000 times as complex as `a`.
+pub fn regex_complexity(hir: &Hir) -> usize {
+    match hir.kind() {
+        HirKind::Repetition(repetition) => {
+            let times = match &repetition.kind {
+                RepetitionKind::Range(RepetitionRange::Exactly(n)) => *n as usize,
+                RepetitionKind::Range(RepetitionRange::AtLeast(n)) => *n as usize + 1,
+                RepetitionKind::Range(RepetitionRange::Bounded(_, n)) => *n as usize,
+                _ => 1,
+            };
+            regex_complexity(&repetition.hir)
+                .saturating_mul(times.max(1))
+                .saturating_add(1)
+        }
+        HirKind::Group(group) => regex_complexity(&group.hir),
+        HirKind::Concat(hirs) | HirKind::Alternation(hirs) => {
+            hirs.iter().fold(1, |complexity, hir| {
+                complexity.saturating_add(regex_complexity(hir))
+            })
+        }
+        _ => 1,
+    }
+}
+
+impl Validate for KeyHintsRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError> {
+        validate_key(&self.key, SearchMethod::Naive, limits)
+    }
+}
+
+impl Validate for RetrievedInfoRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError> {
+        validate_key(
+            &self.key,
+            self.advanced_search_options.search_method,
+            limits,
+        )?;
+        validate_page(self.offset, self.page_size, limits)
+    }
+}
+
+impl Validate for MoreLikeThisRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError> {
+        validate_page(self.offset, self.page_size, limits)
+    }
+}
+
+impl Validate for SearchRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError> {
+        let mut clause_count = 0;
+        if let Some(query) = &self.query {
+            validate_clause(query, &mut clause_count, limits)?;
+        }
+        validate_page(self.pagination.offset, self.pagination.page_size, limits)
+    }
+}
+
+impl Validate for AggregationsRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError> {
+        validate_key(&self.key, self.search_method, limits)?;
+        self.likes_boundaries().or_else(bad_request)?;
+        Ok(())
+    }
+}
+
+impl Validate for ExplainRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError> {
+        validate_key(&self.key, self.search_method, limits)
+    }
+}
+
+impl Validate for AnalyzeRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError> {
+        let text_length = self.text.chars().count();
+        if text_length > limits.max_key_length {
+            return bad_request(format!(
+                "text must not exceed {} chars, got {}",
+                limits.max_key_length, text_length
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Validate for ExportRequest {
+    fn validate(&self, limits: &LimitsConfig) -> Result<(), UserError> {
+        match &self.key {
+            Some(key) => validate_key(key, self.method.into(), limits),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Validate clause and its children, counting clauses on the way
+fn validate_clause(
+    clause: &QueryClause,
+    clause_count: &mut usize,
+    limits: &LimitsConfig,
+) -> Result<(), UserError> {
+    *clause_count += 1;
+    if *clause_count > limits.max_clause_count {
+        return bad_request(format!(
+            "Query must not contain more than {} clauses",
+            limits.max_clause_count
+        ));
+    }
+    match clause {
+        QueryClause::Match(MatchClause { key, method, .. }) => {
+            validate_key(key, (*method).into(), limits)
+        }
+        QueryClause::Bool(bool_clause) => bool_clause
+            .must
+            .iter()
+            .chain(&bool_clause.should)
+            .chain(&bool_clause.must_not)
+            .try_for_each(|clause| validate_clause(clause, clause_count, limits)),
+        QueryClause::MatchAll {} => Ok(()),
+    }
+}
+
+#[cfg(test)]
+#[path = "./validation_test.rs"]
+mod validation_test;
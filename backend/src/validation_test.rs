@@ -0,0 +1,94 @@
+use super::*;
+use crate::interfaces::*;
+
+fn retrieved_info_request(key: &str, search_method: SearchMethod) -> RetrievedInfoRequest {
+    RetrievedInfoRequest {
+        key: key.to_string(),
+        advanced_search_options: AdvanceSearchOptions {
+            sort_by: SearchSortBy::Relevance,
+            search_field: SearchField::All,
+            search_method,
+        },
+        offset: 0,
+        cursor: None,
+        page_size: 10,
+    }
+}
+
+#[test]
+fn test_validate_retrieved_info_request() {
+    let limits = LimitsConfig::default();
+    assert!(retrieved_info_request("标题", SearchMethod::Naive)
+        .validate(&limits)
+        .is_ok());
+
+    let mut request = retrieved_info_request("标题", SearchMethod::Naive);
+    request.page_size = 100_000_000;
+    assert!(matches!(
+        request.validate(&limits),
+        Err(UserError::BadRequest(_))
+    ));
+
+    let mut request = retrieved_info_request("标题", SearchMethod::Naive);
+    request.offset = limits.max_offset + 1;
+    assert!(matches!(
+        request.validate(&limits),
+        Err(UserError::BadRequest(_))
+    ));
+
+    for key in ["", "   ", &"标".repeat(limits.max_key_length + 1)] {
+        assert!(matches!(
+            retrieved_info_request(key, SearchMethod::Naive).validate(&limits),
+            Err(UserError::BadRequest(_))
+        ));
+    }
+}
+
+#[test]
+fn test_validate_regex_complexity() {
+    let limits = LimitsConfig::default();
+    assert!(retrieved_info_request("标.*题", SearchMethod::Regex)
+        .validate(&limits)
+        .is_ok());
+    for key in ["(", "(a{100}){100}", "[a-z]{2000}"] {
+        assert!(matches!(
+            retrieved_info_request(key, SearchMethod::Regex).validate(&limits),
+            Err(UserError::BadRequest(_))
+        ));
+    }
+}
+
+#[test]
+fn test_validate_search_request_clause_count() {
+    let limits = LimitsConfig::default();
+    let clauses = (0..limits.max_clause_count)
+        .map(|_| serde_json::json!({ "match": { "key": "标题" } }))
+        .collect::<Vec<_>>();
+    let search_request: SearchRequest = serde_json::from_value(serde_json::json!({
+        "query": { "bool": { "should": clauses } }
+    }))
+    .unwrap();
+    assert!(matches!(
+        search_request.validate(&limits),
+        Err(UserError::BadRequest(_))
+    ));
+}
+
+#[test]
+fn test_validate_regex_guards() {
+    let limits = LimitsConfig::default();
+    for key in [".*标题", "(标|题).*"] {
+        assert!(matches!(
+            retrieved_info_request(key, SearchMethod::Regex).validate(&limits),
+            Err(UserError::BadRequest(_))
+        ));
+    }
+
+    let limits = LimitsConfig {
+        require_regex_literal_prefix: false,
+        ..LimitsConfig::default()
+    };
+    assert!(retrieved_info_request(".*标题", SearchMethod::Regex)
+        .validate(&limits)
+        .is_ok());
+}
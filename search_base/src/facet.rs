@@ -0,0 +1,89 @@
+use tantivy::schema::Facet;
+
+/// Source site facet of an article, derived from the host of its url
+///
+/// Only the last two labels of host are kept, i.e.,
+/// `https://blog.csdn.net/xxx` is `/csdn.net`.
+pub fn source_facet(url: &str) -> Facet {
+    let without_scheme = match url.find("://") {
+        Some(index) => &url[index + 3..],
+        None => url,
+    };
+    let host = without_scheme
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = host.split(':').next().unwrap_or_default();
+    let labels = host.rsplit('.').take(2).collect::<Vec<_>>();
+    let site = labels.into_iter().rev().collect::<Vec<_>>().join(".");
+    if site.is_empty() {
+        Facet::from_path(vec!["unknown"])
+    } else {
+        Facet::from_path(vec![site.to_lowercase()])
+    }
+}
+
+/// Keywords used to guess the language of code snippets.
+///
+/// Each keyword found in code adds one score to its language.
+const LANGUAGE_KEYWORDS: &[(&str, &[&str])] = &[
+    (
+        "rust",
+        &["fn ", "let mut ", "impl ", "pub fn", "::new(", "println!"],
+    ),
+    (
+        "c++",
+        &["#include", "std::", "cout", "template<", "nullptr"],
+    ),
+    ("c", &["#include", "printf(", "malloc(", "int main("]),
+    (
+        "java",
+        &[
+            "public class",
+            "System.out",
+            "import java.",
+            "@Override",
+            "private ",
+        ],
+    ),
+    ("python", &["def ", "import ", "self.", "elif ", "print("]),
+    ("go", &["func ", "package ", ":= ", "fmt."]),
+    (
+        "javascript",
+        &["function ", "const ", "=> ", "console.log", "var "],
+    ),
+    ("php", &["<?php", "$this->", "echo "]),
+    (
+        "sql",
+        &["SELECT ", "FROM ", "WHERE ", "INSERT INTO", "CREATE TABLE"],
+    ),
+    ("shell", &["#!/bin/", "echo $", "sudo ", "apt-get"]),
+];
+
+/// Guessed programming language facet of code in article, e.g., `/rust`
+///
+/// Returns `None` if code is empty or no language can be told.
+pub fn language_facet(code: &str) -> Option<Facet> {
+    if code.trim().is_empty() {
+        return None;
+    }
+    LANGUAGE_KEYWORDS
+        .iter()
+        .map(|(language, keywords)| {
+            let score = keywords
+                .iter()
+                .filter(|keyword| code.contains(*keyword))
+                .count();
+            (language, score)
+        })
+        // keep the first language with max score
+        .fold(
+            None,
+            |best: Option<(&&str, usize)>, (language, score)| match best {
+                Some((_, best_score)) if best_score >= score => best,
+                _ => Some((language, score)),
+            },
+        )
+        .filter(|(_, score)| *score >= 2)
+        .map(|(language, _)| Facet::from_path(vec![*language]))
+}
@@ -1,3 +1,8 @@
+mod facet;
+mod mixed_tokenizer;
+mod normalize;
+mod pinyin_tokenizer;
+
 use std::path::Path;
 use std::sync::Arc;
 
@@ -9,18 +14,53 @@ use tantivy::{
         Field, IndexRecordOption, Schema, TextFieldIndexing, TextOptions, FAST, INDEXED, STORED,
         STRING,
     },
-    tokenizer::SimpleTokenizer,
+    tokenizer::{SimpleTokenizer, TokenizerManager},
     Index,
 };
 
+pub use facet::{language_facet, source_facet};
+pub use mixed_tokenizer::{MixedTokenizer, TEXT_TOKENIZER};
+pub use normalize::NormalizingTokenizer;
+pub use pinyin_tokenizer::{full_pinyin_terms, pinyin_terms, PinyinTokenizer, PINYIN_TOKENIZER};
+
+/// Tokenizer for code
+pub const CODE_TOKENIZER: &str = "naivetokenizer";
+
+/// CANG_JIE with each `TokenizerOption`, which are registered only to
+/// compare how they split text, see [`tokenizer_names`]
+const CANG_JIE_VARIANTS: [(&str, TokenizerOption); 6] = [
+    ("CANG_JIE_ALL", TokenizerOption::All),
+    ("CANG_JIE_DEFAULT", TokenizerOption::Default { hmm: false }),
+    (
+        "CANG_JIE_DEFAULT_HMM",
+        TokenizerOption::Default { hmm: true },
+    ),
+    (
+        "CANG_JIE_FOR_SEARCH",
+        TokenizerOption::ForSearch { hmm: false },
+    ),
+    (
+        "CANG_JIE_FOR_SEARCH_HMM",
+        TokenizerOption::ForSearch { hmm: true },
+    ),
+    (CANG_JIE, TokenizerOption::Unicode),
+];
+
 #[derive(Clone, Copy)]
 pub struct ProjectDocument {
     pub title: Field,
+    /// Pinyin of title, indexed from the same text as title, see
+    /// [`PinyinTokenizer`]
+    pub title_pinyin: Field,
     pub body: Field,
     pub code: Field,
     pub url: Field,
     pub time: Field,
     pub likes: Field,
+    /// Source site, see [`source_facet`]
+    pub source: Field,
+    /// Programming language of code, see [`language_facet`]
+    pub language: Field,
 }
 
 /// Schema for search engine
@@ -31,7 +71,16 @@ fn schema() -> (Schema, ProjectDocument) {
         "title",
         TextOptions::default().set_stored().set_indexing_options(
             TextFieldIndexing::default()
-                .set_tokenizer(CANG_JIE)
+                .set_tokenizer(TEXT_TOKENIZER)
+                .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+        ),
+    );
+
+    let title_pinyin = schema_builder.add_text_field(
+        "title_pinyin",
+        TextOptions::default().set_indexing_options(
+            TextFieldIndexing::default()
+                .set_tokenizer(PINYIN_TOKENIZER)
                 .set_index_option(IndexRecordOption::WithFreqsAndPositions),
         ),
     );
@@ -40,7 +89,7 @@ fn schema() -> (Schema, ProjectDocument) {
         "body",
         TextOptions::default().set_stored().set_indexing_options(
             TextFieldIndexing::default()
-                .set_tokenizer(CANG_JIE)
+                .set_tokenizer(TEXT_TOKENIZER)
                 .set_index_option(IndexRecordOption::WithFreqsAndPositions),
         ),
     );
@@ -50,43 +99,77 @@ fn schema() -> (Schema, ProjectDocument) {
         TextOptions::default().set_stored().set_indexing_options(
             TextFieldIndexing::default()
                 // untokenized
-                .set_tokenizer("naivetokenizer")
+                .set_tokenizer(CODE_TOKENIZER)
                 .set_index_option(IndexRecordOption::WithFreqsAndPositions),
         ),
     );
 
-    let url = schema_builder.add_text_field(
-        "url",
-        TextOptions::default().set_stored(), // default impl does not gen index
-    );
+    // Indexed untokenized, so that url can be used as the stable identity of an article
+    let url = schema_builder.add_text_field("url", STRING | STORED);
 
     let time = schema_builder.add_date_field("time", INDEXED | STORED | FAST);
 
     let likes = schema_builder.add_u64_field("likes", FAST | STORED);
 
+    let source = schema_builder.add_facet_field("source", INDEXED);
+
+    let language = schema_builder.add_facet_field("language", INDEXED);
+
     let project_document = ProjectDocument {
         title,
+        title_pinyin,
         body,
         code,
         url,
         time,
         likes,
+        source,
+        language,
     };
 
     (schema_builder.build(), project_document)
 }
 
-/// Tokenizer for Chinese sentences
-fn tokenizer() -> CangJieTokenizer {
+/// Tokenizer for Chinese sentences, folding traditional Chinese and
+/// full-width ASCII first
+fn tokenizer(jieba: Arc<Jieba>, option: TokenizerOption) -> NormalizingTokenizer<CangJieTokenizer> {
+    NormalizingTokenizer::new(CangJieTokenizer {
+        worker: jieba,
+        option,
+    })
+}
+
+/// Register tokenizers of [`tokenizer_names`] to `tokenizer_manager`
+fn register_tokenizers(tokenizer_manager: &TokenizerManager) {
     // Modify this to configure Chinese dict
-    let jieba_dict = Jieba::new();
+    let jieba = Arc::new(Jieba::new());
 
-    CangJieTokenizer {
-        worker: Arc::new(jieba_dict),
-        option: TokenizerOption::Unicode,
+    tokenizer_manager.register(
+        TEXT_TOKENIZER,
+        NormalizingTokenizer::new(MixedTokenizer::new(jieba.clone(), true)),
+    );
+    tokenizer_manager.register(CODE_TOKENIZER, SimpleTokenizer);
+    tokenizer_manager.register(PINYIN_TOKENIZER, NormalizingTokenizer::new(PinyinTokenizer));
+    for (name, option) in CANG_JIE_VARIANTS {
+        tokenizer_manager.register(name, tokenizer(jieba.clone(), option));
     }
 }
 
+/// Names of tokenizers registered by [`index`], i.e., those used by fields
+/// followed by variants of CANG_JIE
+pub fn tokenizer_names() -> Vec<&'static str> {
+    let mut tokenizer_names = vec![TEXT_TOKENIZER, CODE_TOKENIZER, PINYIN_TOKENIZER];
+    tokenizer_names.extend(CANG_JIE_VARIANTS.iter().map(|(name, _)| *name));
+    tokenizer_names
+}
+
+/// Tokenizers registered by [`index`], without opening an index
+pub fn tokenizer_manager() -> TokenizerManager {
+    let tokenizer_manager = TokenizerManager::default();
+    register_tokenizers(&tokenizer_manager);
+    tokenizer_manager
+}
+
 /// Index for search engine.
 ///
 /// Returns the `tantivy::Index` stucture for reading/writing
@@ -94,13 +177,8 @@ fn tokenizer() -> CangJieTokenizer {
 pub fn index<P: AsRef<Path>>(directory_path: P) -> tantivy::Result<(Index, ProjectDocument)> {
     let (schema, project_document) = schema();
 
-    let cang_jie_tokenizer = tokenizer();
-
     let index = Index::open_or_create(MmapDirectory::open(directory_path)?, schema)?;
-    index.tokenizers().register(CANG_JIE, cang_jie_tokenizer);
-    index
-        .tokenizers()
-        .register("naivetokenizer", SimpleTokenizer);
+    register_tokenizers(index.tokenizers());
 
     Ok((index, project_document))
 }
@@ -0,0 +1,188 @@
+use jieba_rs::Jieba;
+use rust_stemmers::{Algorithm, Stemmer};
+use std::sync::Arc;
+use tantivy::tokenizer::{
+    BoxTokenStream, PreTokenizedStream, PreTokenizedString, Token, Tokenizer,
+};
+
+/// Tokenizer of titles and bodies, replacing CANG_JIE, so that index built
+/// by CANG_JIE must be rebuilt
+pub const TEXT_TOKENIZER: &str = "mixed";
+
+/// Tokenizer of text mixing Chinese and Latin scripts, e.g.,
+/// `SpringBoot2.x整合Redis`
+///
+/// - Runs of Chinese chars are segmented by jieba, e.g., `整合`
+/// - Runs of other letters and digits are lowercase words, e.g.,
+///   `springboot`, `redis`, optionally stemmed as English, e.g., `tokenizers`
+///   to `token`
+/// - Versions are words of their own, e.g., `2.x` of `SpringBoot2.x`, `3.10`
+///   of `Python3.10`, and `1.2` of `v1.2`, while digits not followed by a
+///   version part stay in words, e.g., `vue3`, `k8s`
+/// - Words followed by `++`, and single letters followed by `#`, keep them,
+///   e.g., `c++`, `c#`
+///
+/// Other chars, e.g., `/` of `async/await`, separate tokens.
+#[derive(Clone)]
+pub struct MixedTokenizer {
+    jieba: Arc<Jieba>,
+    stemming: bool,
+}
+
+impl MixedTokenizer {
+    pub fn new(jieba: Arc<Jieba>, stemming: bool) -> Self {
+        Self { jieba, stemming }
+    }
+}
+
+impl Tokenizer for MixedTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        let stemmer = if self.stemming {
+            Some(Stemmer::create(Algorithm::English))
+        } else {
+            None
+        };
+        let mut tokens: Vec<Token> = vec![];
+        let mut push = |text: String, offset_from: usize, offset_to: usize| {
+            let position = tokens.len();
+            tokens.push(Token {
+                offset_from,
+                offset_to,
+                position,
+                text,
+                position_length: 1,
+            })
+        };
+        let mut offset = 0;
+        while let Some(c) = text[offset..].chars().next() {
+            if is_han(c) {
+                let end = run_end(text, offset, is_han);
+                let mut word_offset = offset;
+                for word in self.jieba.cut(&text[offset..end], true) {
+                    push(word.to_string(), word_offset, word_offset + word.len());
+                    word_offset += word.len();
+                }
+                offset = end;
+            } else if is_word_char(c) {
+                let end = run_end(text, offset, is_word_char);
+                let word = &text[offset..end];
+                if let Some(version_end) = version_end(text, end) {
+                    // Version starts at trailing digits of word
+                    let digits_start =
+                        offset + word.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+                    let prefix = &text[offset..digits_start];
+                    if !prefix.is_empty() && !prefix.eq_ignore_ascii_case("v") {
+                        push(stem(&stemmer, prefix), offset, digits_start);
+                    }
+                    push(
+                        text[digits_start..version_end].to_lowercase(),
+                        digits_start,
+                        version_end,
+                    );
+                    offset = version_end;
+                    continue;
+                }
+                let suffix = &text[end..];
+                let suffix_length = if suffix.starts_with("++") {
+                    2
+                } else if suffix.starts_with('#')
+                    && word.len() == 1
+                    && word.as_bytes()[0].is_ascii_alphabetic()
+                {
+                    1
+                } else {
+                    0
+                };
+                if suffix_length > 0 {
+                    push(
+                        text[offset..end + suffix_length].to_lowercase(),
+                        offset,
+                        end + suffix_length,
+                    );
+                } else {
+                    push(stem(&stemmer, word), offset, end);
+                }
+                offset = end + suffix_length;
+            } else {
+                offset += c.len_utf8();
+            }
+        }
+        BoxTokenStream::from(PreTokenizedStream::from(PreTokenizedString {
+            text: text.to_string(),
+            tokens,
+        }))
+    }
+}
+
+/// Whether `c` is a CJK unified ideograph, including extensions and
+/// compatibility ideographs
+fn is_han(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{20000}'..='\u{2FFFF}'
+    )
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() && !is_han(c)
+}
+
+/// End of the run of chars satisfying `predicate` starting at `start`
+fn run_end(text: &str, start: usize, predicate: fn(char) -> bool) -> usize {
+    text[start..]
+        .char_indices()
+        .find(|&(_, c)| !predicate(c))
+        .map_or(text.len(), |(index, _)| start + index)
+}
+
+/// End of the version whose major part ends a word at `offset`, if the word
+/// ends with digits followed by parts like `.10` or `.x`
+fn version_end(text: &str, offset: usize) -> Option<usize> {
+    if !text[..offset].ends_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut end = offset;
+    loop {
+        let rest = &text[end..];
+        if !rest.starts_with('.') {
+            break;
+        }
+        let digits = rest[1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len() - 1);
+        let part_length = if digits > 0 {
+            digits
+        } else if rest[1..].starts_with(['x', 'X'])
+            && !rest[2..].starts_with(|c: char| is_word_char(c))
+        {
+            1
+        } else {
+            break;
+        };
+        end += 1 + part_length;
+    }
+    if end > offset {
+        Some(end)
+    } else {
+        None
+    }
+}
+
+/// Lowercase `word`, stemmed as English if `stemmer` is given and `word` is
+/// made of ASCII letters
+fn stem(stemmer: &Option<Stemmer>, word: &str) -> String {
+    let word = word.to_lowercase();
+    match stemmer {
+        Some(stemmer) if word.bytes().all(|byte| byte.is_ascii_lowercase()) => {
+            stemmer.stem(&word).into_owned()
+        }
+        _ => word,
+    }
+}
+
+#[cfg(test)]
+#[path = "./mixed_tokenizer_test.rs"]
+mod mixed_tokenizer_test;
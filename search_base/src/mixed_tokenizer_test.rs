@@ -0,0 +1,66 @@
+use super::*;
+
+fn tokenizer(stemming: bool) -> MixedTokenizer {
+    MixedTokenizer::new(Arc::new(Jieba::new()), stemming)
+}
+
+fn texts(tokenizer: &MixedTokenizer, text: &str) -> Vec<String> {
+    let mut texts = vec![];
+    tokenizer
+        .token_stream(text)
+        .process(&mut |token| texts.push(token.text.clone()));
+    texts
+}
+
+#[test]
+fn test_mixed_titles() {
+    let tokenizer = tokenizer(false);
+    assert_eq!(
+        texts(&tokenizer, "Rust中的async/await详解"),
+        vec!["rust", "中", "的", "async", "await", "详解"]
+    );
+    assert_eq!(
+        texts(&tokenizer, "SpringBoot2.x整合Redis"),
+        vec!["springboot", "2.x", "整合", "redis"]
+    );
+    assert_eq!(
+        texts(&tokenizer, "Python3.10与C++/C#入门"),
+        vec!["python", "3.10", "与", "c++", "c#", "入门"]
+    );
+}
+
+#[test]
+fn test_versions() {
+    let tokenizer = tokenizer(false);
+    assert_eq!(texts(&tokenizer, "Vue3 k8s"), vec!["vue3", "k8s"]);
+    assert_eq!(
+        texts(&tokenizer, "v1.2.3 升级到 Node 18.X"),
+        vec!["1.2.3", "升级", "到", "node", "18.x"]
+    );
+    // Dots not followed by version parts are separators
+    assert_eq!(
+        texts(&tokenizer, "node.js ASP.NET 2.xml"),
+        vec!["node", "js", "asp", "net", "2", "xml"]
+    );
+}
+
+#[test]
+fn test_stemming() {
+    assert_eq!(
+        texts(&tokenizer(true), "Running Tokenizers v2.0"),
+        vec!["run", "token", "2.0"]
+    );
+    assert_eq!(
+        texts(&tokenizer(false), "Running Tokenizers"),
+        vec!["running", "tokenizers"]
+    );
+}
+
+#[test]
+fn test_offsets() {
+    let mut tokens = vec![];
+    tokenizer(false)
+        .token_stream("Rust中的C++")
+        .process(&mut |token| tokens.push((token.offset_from, token.offset_to, token.position)));
+    assert_eq!(tokens, vec![(0, 4, 0), (4, 7, 1), (7, 10, 2), (10, 13, 3)]);
+}
@@ -0,0 +1,124 @@
+use tantivy::tokenizer::{
+    BoxTokenStream, PreTokenizedStream, PreTokenizedString, Token, Tokenizer,
+};
+use zhconv::{get_builtin_converter, variant::Variant};
+
+/// Tokenizer normalizing text before tokenizing it by `inner`, so that
+/// variants of the same text produce the same terms:
+///
+/// - Traditional Chinese is folded to simplified, including regional words,
+///   e.g., `資料庫` to `数据库`
+/// - Full-width ASCII is folded to half-width, e.g., `ｒｕｓｔ` to `rust`
+///
+/// Offsets of tokens still refer to the original text, so that snippets are
+/// highlighted correctly. Since terms change, index must be rebuilt once
+/// this is applied to a field.
+#[derive(Clone)]
+pub struct NormalizingTokenizer<T> {
+    inner: T,
+}
+
+impl<T> NormalizingTokenizer<T> {
+    pub fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<T: Tokenizer + Clone> Tokenizer for NormalizingTokenizer<T> {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        let normalized = normalize(text);
+        let mut tokens = vec![];
+        self.inner
+            .token_stream(&normalized.text)
+            .process(&mut |token| {
+                tokens.push(Token {
+                    offset_from: normalized.original_offset_from(token.offset_from),
+                    offset_to: normalized.original_offset_to(token.offset_to),
+                    ..token.clone()
+                })
+            });
+        BoxTokenStream::from(PreTokenizedStream::from(PreTokenizedString {
+            text: text.to_string(),
+            tokens,
+        }))
+    }
+}
+
+/// Normalized text, with offsets of text aligned to original text
+struct Normalized {
+    text: String,
+    /// Pairs of offsets in normalized and original text at which both are
+    /// aligned, in ascending order, including starts and ends of texts
+    alignments: Vec<(usize, usize)>,
+}
+
+impl Normalized {
+    /// Offset in original text of the start of a token at `offset`
+    fn original_offset_from(&self, offset: usize) -> usize {
+        let index = self
+            .alignments
+            .partition_point(|&(normalized, _)| normalized <= offset);
+        self.alignments[index.saturating_sub(1)].1
+    }
+
+    /// Offset in original text of the end of a token at `offset`
+    fn original_offset_to(&self, offset: usize) -> usize {
+        let index = self
+            .alignments
+            .partition_point(|&(normalized, _)| normalized < offset);
+        self.alignments[index.min(self.alignments.len() - 1)].1
+    }
+}
+
+/// Normalize `text` as [`NormalizingTokenizer`] does
+fn normalize(text: &str) -> Normalized {
+    let mut normalized = Normalized {
+        text: String::with_capacity(text.len()),
+        alignments: vec![],
+    };
+    let mut last = 0;
+    // `search` is what `convert` replaces, which tells replaced spans
+    for (start, end, target) in get_builtin_converter(Variant::ZhCN).search(text) {
+        fold_width(&text[last..start], last, &mut normalized);
+        let source = &text[start..end];
+        if source.chars().count() == target.chars().count() {
+            // Aligned char by char, so that a token of some chars of a word
+            // is highlighted as it is
+            for ((offset, _), target_char) in source.char_indices().zip(target.chars()) {
+                normalized
+                    .alignments
+                    .push((normalized.text.len(), start + offset));
+                normalized.text.push(target_char);
+            }
+        } else {
+            normalized.alignments.push((normalized.text.len(), start));
+            normalized.text.push_str(target);
+        }
+        last = end;
+    }
+    fold_width(&text[last..], last, &mut normalized);
+    normalized
+        .alignments
+        .push((normalized.text.len(), text.len()));
+    normalized
+}
+
+/// Append `text` at `offset` of original text to `normalized`, folding
+/// full-width ASCII to half-width
+fn fold_width(text: &str, offset: usize, normalized: &mut Normalized) {
+    for (char_offset, c) in text.char_indices() {
+        normalized
+            .alignments
+            .push((normalized.text.len(), offset + char_offset));
+        normalized.text.push(match c {
+            // Ideographic space
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        });
+    }
+}
+
+#[cfg(test)]
+#[path = "./normalize_test.rs"]
+mod normalize_test;
@@ -0,0 +1,36 @@
+use super::*;
+use tantivy::tokenizer::SimpleTokenizer;
+
+fn tokens(text: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    NormalizingTokenizer::new(SimpleTokenizer)
+        .token_stream(text)
+        .process(&mut |token| tokens.push(token.clone()));
+    tokens
+}
+
+#[test]
+fn test_normalize() {
+    assert_eq!(normalize("資料庫連線").text, "数据库连线");
+    assert_eq!(normalize("ｒｕｓｔ　１．５６").text, "rust 1.56");
+    assert_eq!(normalize("数据库 rust").text, "数据库 rust");
+}
+
+#[test]
+fn test_offsets_of_original_text() {
+    let text = "用ｒｕｓｔ寫資料庫";
+    let got = tokens(text);
+    assert_eq!(got.len(), 1);
+    assert_eq!(got[0].text, "用rust写数据库");
+    assert_eq!(&text[got[0].offset_from..got[0].offset_to], text);
+
+    let text = "ＡＢ，資料";
+    let got = tokens(text)
+        .into_iter()
+        .map(|token| (token.text, &text[token.offset_from..token.offset_to]))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        got,
+        vec![("AB".to_string(), "ＡＢ"), ("资料".to_string(), "資料")]
+    );
+}
@@ -0,0 +1,168 @@
+use pinyin::{Pinyin, ToPinyinMulti};
+use std::collections::HashSet;
+use std::sync::OnceLock;
+use tantivy::tokenizer::{
+    BoxTokenStream, PreTokenizedStream, PreTokenizedString, Token, Tokenizer,
+};
+
+/// Tokenizer of pinyin of titles
+pub const PINYIN_TOKENIZER: &str = "pinyin";
+
+/// Tokenizer of pinyin of Chinese text, so that it can be searched by
+/// pinyin typed with IME off
+///
+/// Each Chinese char is a position of tokens of the full pinyin (without
+/// tone, `ü` as `v`) of each of its readings and their initial letters, e.g.,
+/// `数据库` is `shu`/`shuo`/`s`, `ju`/`j` and `ku`/`k`, so that phrases of
+/// [`pinyin_terms`] match, e.g., `shujuku` or `sjk`. Runs of ASCII
+/// alphanumerics are lowercase tokens of their own.
+#[derive(Clone)]
+pub struct PinyinTokenizer;
+
+impl Tokenizer for PinyinTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        let mut tokens: Vec<Token> = vec![];
+        let mut position = 0;
+        let mut push = |text: String, offset_from: usize, offset_to: usize, position: usize| {
+            tokens.push(Token {
+                offset_from,
+                offset_to,
+                position,
+                text,
+                position_length: 1,
+            })
+        };
+        let mut ascii_start = None;
+        for (offset, c) in text.char_indices().chain([(text.len(), ' ')]) {
+            if c.is_ascii_alphanumeric() {
+                ascii_start.get_or_insert(offset);
+                continue;
+            }
+            if let Some(start) = ascii_start.take() {
+                push(text[start..offset].to_lowercase(), start, offset, position);
+                position += 1;
+            }
+            let readings = match c.to_pinyin_multi() {
+                Some(readings) => readings,
+                None => continue,
+            };
+            let mut terms: Vec<String> = vec![];
+            for syllable in readings.into_iter().filter_map(plain) {
+                let initial = syllable[..1].to_string();
+                for term in [syllable, initial] {
+                    if !terms.contains(&term) {
+                        terms.push(term);
+                    }
+                }
+            }
+            for term in terms {
+                push(term, offset, offset + c.len_utf8(), position);
+            }
+            position += 1;
+        }
+        BoxTokenStream::from(PreTokenizedStream::from(PreTokenizedString {
+            text: text.to_string(),
+            tokens,
+        }))
+    }
+}
+
+/// Full pinyin of `reading` without tone, writing `ü` as `v` as typed
+///
+/// Returns `None` for rare readings which can not be typed in ASCII, e.g.,
+/// `ê`.
+fn plain(reading: Pinyin) -> Option<String> {
+    let syllable = reading.plain().replace('ü', "v");
+    if syllable.is_ascii() && !syllable.is_empty() {
+        Some(syllable)
+    } else {
+        None
+    }
+}
+
+/// Every pinyin syllable of Chinese chars
+fn syllables() -> &'static HashSet<String> {
+    static SYLLABLES: OnceLock<HashSet<String>> = OnceLock::new();
+    SYLLABLES.get_or_init(|| {
+        ('\u{3400}'..='\u{9FFF}')
+            .filter_map(|c| c.to_pinyin_multi())
+            .flat_map(|readings| readings.into_iter().filter_map(plain))
+            .collect()
+    })
+}
+
+/// First letters of [`syllables`], i.e., terms typed as initials
+fn initials() -> &'static HashSet<&'static str> {
+    static INITIALS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    INITIALS.get_or_init(|| syllables().iter().map(|syllable| &syllable[..1]).collect())
+}
+
+/// Split `query` of ASCII letters and whitespaces into terms of
+/// [`PinyinTokenizer`], preferring fewer terms, e.g., `shujuku` into `shu`,
+/// `ju`, `ku`, and `sjk` into `s`, `j`, `k`
+///
+/// Returns `None` if `query` contains other chars or can not be split, e.g.,
+/// `ui`, since no syllable starts with `u` or `i`.
+pub fn pinyin_terms(query: &str) -> Option<Vec<String>> {
+    split_pinyin(query, true)
+}
+
+/// Split `query` like [`pinyin_terms`], but into full syllables only, e.g.,
+/// `shujuku` into `shu`, `ju`, `ku`
+///
+/// Returns `None` for initials such as `sjk`, so that this tells whether
+/// `query` is pinyin typed with IME off rather than, e.g., an English word.
+pub fn full_pinyin_terms(query: &str) -> Option<Vec<String>> {
+    split_pinyin(query, false)
+}
+
+/// Split `query` into fewest syllables, and initials if `allow_initials`
+fn split_pinyin(query: &str, allow_initials: bool) -> Option<Vec<String>> {
+    if !query
+        .chars()
+        .all(|c| c.is_ascii_alphabetic() || c.is_whitespace())
+    {
+        return None;
+    }
+    let syllables = syllables();
+    let initials = initials();
+    let is_term =
+        |term: &str| syllables.contains(term) || (allow_initials && initials.contains(term));
+    let max_syllable_length = syllables.iter().map(String::len).max().unwrap_or(0);
+    let mut terms = vec![];
+    for word in query.split_whitespace() {
+        let word = word.to_ascii_lowercase();
+        // Fewest terms covering `word[..end]` and start of the last one
+        let mut splits: Vec<Option<(usize, usize)>> = vec![None; word.len() + 1];
+        splits[0] = Some((0, 0));
+        for end in 1..=word.len() {
+            for start in end.saturating_sub(max_syllable_length)..end {
+                if let Some((count, _)) = splits[start] {
+                    if is_term(&word[start..end])
+                        && splits[end].is_none_or(|(best, _)| count + 1 < best)
+                    {
+                        splits[end] = Some((count + 1, start));
+                    }
+                }
+            }
+        }
+        splits[word.len()]?;
+        let mut word_terms = vec![];
+        let mut end = word.len();
+        while end > 0 {
+            let (_, start) = splits[end]?;
+            word_terms.push(word[start..end].to_string());
+            end = start;
+        }
+        terms.extend(word_terms.into_iter().rev());
+    }
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms)
+    }
+}
+
+#[cfg(test)]
+#[path = "./pinyin_tokenizer_test.rs"]
+mod pinyin_tokenizer_test;
@@ -0,0 +1,48 @@
+use super::*;
+
+fn tokens(text: &str) -> Vec<(String, usize, usize)> {
+    let mut tokens = vec![];
+    PinyinTokenizer
+        .token_stream(text)
+        .process(&mut |token| tokens.push((token.text.clone(), token.offset_from, token.position)));
+    tokens
+}
+
+#[test]
+fn test_tokens() {
+    let got = tokens("Rust数据库");
+    assert!(got.contains(&("rust".to_string(), 0, 0)));
+    assert!(got.contains(&("shu".to_string(), 4, 1)));
+    assert!(got.contains(&("s".to_string(), 4, 1)));
+    assert!(got.contains(&("ju".to_string(), 7, 2)));
+    assert!(got.contains(&("j".to_string(), 7, 2)));
+    assert!(got.contains(&("ku".to_string(), 10, 3)));
+    assert!(got.contains(&("k".to_string(), 10, 3)));
+    assert!(got.iter().all(|(_, _, position)| *position <= 3));
+    assert!(tokens("绿").contains(&("lv".to_string(), 0, 0)));
+    // Readings not typeable in ASCII, e.g., `ê`, are skipped
+    assert!(tokens("欸").iter().all(|(text, _, _)| text.is_ascii()));
+}
+
+#[test]
+fn test_pinyin_terms() {
+    let terms = |query: &str| pinyin_terms(query).map(|terms| terms.join(" "));
+    assert_eq!(terms("shujuku").as_deref(), Some("shu ju ku"));
+    assert_eq!(terms("sjk").as_deref(), Some("s j k"));
+    assert_eq!(terms("ShuJu k").as_deref(), Some("shu ju k"));
+    assert_eq!(terms("xian").as_deref(), Some("xian"));
+    assert_eq!(terms("ui"), None);
+    assert_eq!(terms("k8s"), None);
+    assert_eq!(terms("数据库"), None);
+    assert_eq!(terms(" "), None);
+}
+
+#[test]
+fn test_full_pinyin_terms() {
+    let terms = |query: &str| full_pinyin_terms(query).map(|terms| terms.join(" "));
+    assert_eq!(terms("shujuku").as_deref(), Some("shu ju ku"));
+    assert_eq!(terms("ShuJu ku").as_deref(), Some("shu ju ku"));
+    assert_eq!(terms("sjk"), None);
+    assert_eq!(terms("shuju k"), None);
+    assert_eq!(terms("databse"), None);
+}
@@ -1,9 +1,7 @@
 use chrono::{DateTime, NaiveDateTime, Utc};
 use search_base::*;
 use serde::Deserialize;
-use serde_json::Value;
-use std::fs;
-use std::io::Read;
+use std::{env, fs};
 use tantivy::doc;
 
 #[derive(Deserialize)]
@@ -22,12 +20,37 @@ struct IndexerConfig {
     data_path: String,
 }
 
+/// Print tokens of `text` by every tokenizer registered by [`index`]
+fn analyze(text: &str) {
+    let tokenizer_manager = tokenizer_manager();
+    for tokenizer_name in tokenizer_names() {
+        println!("{}:", tokenizer_name);
+        let tokenizer = tokenizer_manager
+            .get(tokenizer_name)
+            .unwrap_or_else(|| panic!("Unable to find tokenizer {}.", tokenizer_name));
+        tokenizer.token_stream(text).process(&mut |token| {
+            println!(
+                "  {}\t[{}, {})\t{}",
+                token.position, token.offset_from, token.offset_to, token.text
+            )
+        });
+    }
+}
+
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("analyze") {
+        if args.len() < 3 {
+            eprintln!("Usage: {} analyze <text>...", args[0]);
+            std::process::exit(2);
+        }
+        analyze(&args[2..].join(" "));
+        return;
+    }
+
     let config_file_path = "./indexer-config.toml";
-    let config_str = fs::read_to_string(&config_file_path).expect(&format!(
-        "Unable to open config file at {}.",
-        &config_file_path
-    ));
+    let config_str = fs::read_to_string(config_file_path)
+        .unwrap_or_else(|_| panic!("Unable to open config file at {}.", &config_file_path));
     let config: IndexerConfig = match toml::from_str(&config_str) {
         Ok(config) => config,
         Err(error) => panic!("Config file parse failed: {}", error),
@@ -52,7 +75,7 @@ fn main() {
     // let contents = String::from_utf8_lossy(&buf);
     // let contents = file.read_to_string().unwrap();
     let contents = fs::read_to_string(&config.data_path).unwrap();
-    let mut lines = contents.lines();
+    let lines = contents.lines();
     // let mut contents = String::new();
     // file.read_to_string(&mut contents).unwrap();
     // let mut lines = contents.lines();
@@ -61,7 +84,7 @@ fn main() {
     let mut a = 0;
     for line in lines {
         a += 1;
-        if line.trim().len() == 0 {
+        if line.trim().is_empty() {
             continue;
         }
         let json_object: ArticleInfo = if let Ok(json_object) = serde_json::from_str(line.trim()) {
@@ -72,20 +95,27 @@ fn main() {
         };
         let naive_datetime = NaiveDateTime::from_timestamp(json_object.date as i64, 0);
         let date = DateTime::from_utc(naive_datetime, Utc);
-        index_writer.add_document(doc!(
+        let code = json_object.code.concat();
+        let mut document = doc!(
+            base_result.1.title_pinyin => json_object.title.clone(),
             base_result.1.title => json_object.title,
             base_result.1.body => json_object.content.concat(),
-            base_result.1.code => json_object.code.concat(),
+            base_result.1.source => source_facet(&json_object.url),
             base_result.1.url => json_object.url,
             base_result.1.time => date,
             base_result.1.likes => json_object.views,
-        ));
+        );
+        if let Some(language) = language_facet(&code) {
+            document.add_facet(base_result.1.language, language);
+        }
+        document.add_text(base_result.1.code, &code);
+        index_writer.add_document(document);
     }
 
     // commit index writer
     match index_writer.commit() {
-        Ok(i) => {}
-        Err(j) => {
+        Ok(_) => {}
+        Err(_) => {
             panic!("commit error!");
         }
     }